@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::{collections::{HashMap, HashSet}, ops::RangeInclusive};
 use crate::{grid::Grid, point::Point};
 
 #[aoc_generator(day10)]
@@ -40,13 +40,181 @@ fn solve(grid: &Grid) -> (usize, usize) {
     (total, total2)
 }
 
+type HeightTest = Box<dyn Fn(u8) -> bool>;
+
+/// Hiking trails over a height map.  Heights are digits, or letters for heights past 9 (base 36);
+/// anything else is impassable.  A trail starts on a trailhead, climbs by an allowed slope each
+/// step, and ends as soon as it reaches a summit.
+pub struct Trails {
+    grid: Grid,
+    heights: HashMap<Point, u8>,
+    slope: RangeInclusive<u8>,
+    trailhead: HeightTest,
+    summit: HeightTest,
+}
+
+impl Trails {
+    /// The puzzle's rules: climb by exactly 1 from 0 to 9
+    pub fn new(grid: &Grid) -> Trails {
+        let heights = grid.map.iter()
+            .filter_map(|(c, points)| c.to_digit(36).map(|h| (h as u8, points)))
+            .flat_map(|(h, points)| points.iter().map(move |p| (*p, h)))
+            .collect();
+        Trails {
+            grid: grid.clone(),
+            heights,
+            slope: 1..=1,
+            trailhead: Box::new(|h| h == 0),
+            summit: Box::new(|h| h == 9),
+        }
+    }
+
+    /// How much each step may climb.  Trails must always climb, so they can't loop.
+    pub fn with_slope(mut self, slope: RangeInclusive<u8>) -> Trails {
+        assert!(*slope.start() > 0, "trails must climb");
+        self.slope = slope;
+        self
+    }
+
+    pub fn with_trailheads(mut self, test: impl Fn(u8) -> bool + 'static) -> Trails {
+        self.trailhead = Box::new(test);
+        self
+    }
+
+    pub fn with_summits(mut self, test: impl Fn(u8) -> bool + 'static) -> Trails {
+        self.summit = Box::new(test);
+        self
+    }
+
+    pub fn height(&self, p: &Point) -> Option<u8> {
+        self.heights.get(p).copied()
+    }
+
+    fn is_summit(&self, p: &Point) -> bool {
+        self.height(p).is_some_and(|h| (self.summit)(h))
+    }
+
+    pub fn trailheads(&self) -> Vec<Point> {
+        let mut heads: Vec<Point> = self.heights.iter()
+            .filter(|(_, h)| (self.trailhead)(**h))
+            .map(|(p, _)| *p)
+            .collect();
+        heads.sort_by_key(|p| (-p.y, p.x));
+        heads
+    }
+
+    /// Where a trail can go next from p
+    pub fn steps(&self, p: &Point) -> Vec<Point> {
+        let Some(h) = self.height(p) else { return Vec::new() };
+        if self.is_summit(p) {
+            return Vec::new();
+        }
+        let mut next: Vec<Point> = p.neighbors_straight().into_iter()
+            .filter(|n| self.height(n).is_some_and(|h2| h2 > h && self.slope.contains(&(h2 - h))))
+            .collect();
+        next.sort_by_key(|p| (-p.y, p.x));
+        next
+    }
+
+    /// Distinct summits reachable from a trailhead
+    pub fn score(&self, head: &Point) -> usize {
+        let mut seen = HashSet::from([*head]);
+        let mut stack = vec![*head];
+        let mut summits = 0;
+        while let Some(p) = stack.pop() {
+            if self.is_summit(&p) {
+                summits += 1;
+            }
+            for n in self.steps(&p) {
+                if seen.insert(n) {
+                    stack.push(n);
+                }
+            }
+        }
+        summits
+    }
+
+    /// Number of distinct trails from every cell to any summit, filled in from the highest cells down
+    pub fn ratings(&self) -> HashMap<Point, usize> {
+        let mut cells: Vec<(&Point, &u8)> = self.heights.iter().collect();
+        cells.sort_by_key(|(_, h)| std::cmp::Reverse(**h));
+        let mut ratings = HashMap::new();
+        for (p, _) in cells {
+            let rating = if self.is_summit(p) {
+                1
+            } else {
+                self.steps(p).iter().map(|n| ratings[n]).sum()
+            };
+            ratings.insert(*p, rating);
+        }
+        ratings
+    }
+
+    pub fn rating(&self, head: &Point) -> usize {
+        self.ratings().get(head).copied().unwrap_or(0)
+    }
+
+    pub fn total_score(&self) -> usize {
+        self.trailheads().iter().map(|h| self.score(h)).sum()
+    }
+
+    pub fn total_rating(&self) -> usize {
+        let ratings = self.ratings();
+        self.trailheads().iter().map(|h| ratings[h]).sum()
+    }
+
+    /// Every trail from a trailhead, each as the list of cells from the head to its summit
+    pub fn trails(&self, head: &Point) -> Vec<Vec<Point>> {
+        let mut trails = Vec::new();
+        let mut stack = vec![vec![*head]];
+        while let Some(trail) = stack.pop() {
+            let last = trail.last().unwrap();
+            if self.is_summit(last) {
+                trails.push(trail);
+                continue;
+            }
+            for n in self.steps(last).into_iter().rev() {
+                let mut longer = trail.clone();
+                longer.push(n);
+                stack.push(longer);
+            }
+        }
+        trails
+    }
+
+    /// The map with everything but the trail blanked out
+    pub fn render(&self, trail: &[Point]) -> String {
+        let keep: HashSet<&Point> = trail.iter().collect();
+        let mut grid = self.grid.clone();
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let p = Point::new(x, y);
+                if !keep.contains(&p) {
+                    grid.set(&p, '.');
+                }
+            }
+        }
+        grid.to_string()
+    }
+}
+
 #[aoc(day10, part1)]
 fn part1(grid: &Grid) -> usize {
-    solve(grid).0
+    Trails::new(grid).total_score()
 }
 
 #[aoc(day10, part2)]
 fn part2(grid: &Grid) -> usize {
+    Trails::new(grid).total_rating()
+}
+
+#[aoc(day10, part1, dfs)]
+fn part1_dfs(grid: &Grid) -> usize {
+    solve(grid).0
+}
+
+#[aoc(day10, part2, dfs)]
+fn part2_dfs(grid: &Grid) -> usize {
     solve(grid).1
 }
 
@@ -120,5 +288,58 @@ const SAMPLE81: &str = SAMPLE36;
         assert_eq!(part2(&input_generator(SAMPLE81)), 81);
     }
 
+    #[test]
+    fn dfs_samples() {
+        assert_eq!(part1_dfs(&input_generator(SAMPLE36)), 36);
+        assert_eq!(part2_dfs(&input_generator(SAMPLE81)), 81);
+    }
+
+    #[test]
+    fn trail_listing() {
+        let trails = Trails::new(&input_generator(SAMPLE3));
+        let heads = trails.trailheads();
+        assert_eq!(heads, vec![Point::new(5, 6)]);
+        let all = trails.trails(&heads[0]);
+        assert_eq!(all.len(), 3);
+        assert!(all.iter().all(|t| t.len() == 10));
+        assert_eq!(trails.render(&all[0]), ".....0.
+..4321.
+..5....
+..6....
+..7....
+..8....
+..9....");
+        assert_eq!(trails.score(&heads[0]), 1);
+        assert_eq!(trails.rating(&heads[0]), 3);
+    }
+
+    #[test]
+    fn custom_rules() {
+        // Heights past 9, steps of 2, and the top is z
+        let grid = input_generator("02468acegikmoqsuwyz
+1...................");
+        let trails = Trails::new(&grid).with_slope(1..=2).with_summits(|h| h == 35);
+        assert_eq!(trails.total_score(), 1);
+        // The 1 is a dead end
+        assert_eq!(trails.total_rating(), 1);
+
+        let steep = Trails::new(&grid).with_slope(2..=2).with_summits(|h| h == 34);
+        assert_eq!(steep.total_rating(), 1);
+
+        // Any low point will do as a trailhead
+        let anywhere = Trails::new(&input_generator(SAMPLE36)).with_trailheads(|h| h <= 1);
+        assert!(anywhere.total_rating() > 81);
+        let listed: usize = anywhere.trailheads().iter().map(|h| anywhere.trails(h).len()).sum();
+        assert_eq!(listed, anywhere.total_rating());
+    }
 
+    #[test]
+    fn generated_trails() {
+        for seed in 0..10 {
+            let map = crate::gen::trail_map(seed, 30, 20, 12);
+            let grid = input_generator(&map.input);
+            assert_eq!((part1(&grid), part2(&grid)), map.answer);
+            assert_eq!((part1_dfs(&grid), part2_dfs(&grid)), map.answer);
+        }
+    }
 }
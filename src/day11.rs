@@ -1,6 +1,9 @@
-use std::collections::HashMap;
+use smallvec::smallvec;
 
-type Stones = Vec<u64>;
+use num::BigUint;
+use crate::evolver::{Count, CountingEvolver, Offspring, Overflow, Strategy};
+
+pub type Stones = Vec<u64>;
 
 #[aoc_generator(day11)]
 fn input_generator(input: &str) -> Stones {
@@ -8,52 +11,44 @@ fn input_generator(input: &str) -> Stones {
 }
 
 
-struct StonePopulation {
-    // How many stones of each number do we have?
-    stones: HashMap<u64, u64>,
-}
-
-impl StonePopulation {
-    fn new(inp: &Stones) -> StonePopulation {
-        let mut stones = HashMap::new();
-        for s in inp.iter() {
-            *stones.entry(*s).or_insert(0u64) += 1u64;
+type StonePopulation<C = u64> = CountingEvolver<u64, fn(&u64) -> Option<Offspring<u64>>, C>;
+
+// Each blink, every stone changes according to the first rule that applies.
+// None if the stone's new number doesn't fit in a u64.
+fn blink(stone: &u64) -> Option<Offspring<u64>> {
+    if *stone == 0 {
+        Some(smallvec![1])
+    } else {
+        let log = stone.checked_ilog10().unwrap_or(0) + 1;
+        if log.is_multiple_of(2) {
+            let div = 10_u64.pow(log / 2);
+            Some(smallvec![stone / div, stone % div])
+        } else {
+            stone.checked_mul(2024).map(|s| smallvec![s])
         }
-        StonePopulation { stones }
     }
+}
 
-    fn blink(&mut self) {
-        let mut new_stones = HashMap::new();
-        for (stone, count) in self.stones.iter() {
-            if stone == &0 {
-                *new_stones.entry(1).or_insert(0) += count;
-            } else {
-                let log = stone.checked_ilog10().unwrap_or(0) + 1;
-                if log % 2 == 0 {
-                    let div = 10_u64.pow(log / 2);
-                    *new_stones.entry(stone / div).or_insert(0) += count;
-                    *new_stones.entry(stone % div).or_insert(0) += count;
-                } else {
-                    *new_stones.entry(stone * 2024).or_insert(0) += count;
-                }
-            };
-        }
-        self.stones = new_stones;
-    }
+fn population<C: Count>(inp: &Stones) -> StonePopulation<C> {
+    CountingEvolver::new(blink, inp.iter().copied())
+}
 
-    fn len(&self) -> u64 {
-        self.stones.values().sum()
-    }
 
+pub fn solve_checked(stones : &Stones, blinks: usize) -> Result<u64, Overflow> {
+    population(stones).count_at(blinks)
 }
 
+// Arbitrary-precision counts for when u64 isn't enough
+pub fn solve_big(stones : &Stones, blinks: usize) -> Result<BigUint, Overflow> {
+    population(stones).count_at(blinks)
+}
 
 fn solve(stones : &Stones, blinks: u8) -> u64 {
-    let mut stones = StonePopulation::new(stones);
-    for _ in 0..blinks {
-        stones.blink();
-    }
-    stones.len()
+    solve_checked(stones, blinks as usize).expect("Too many stones")
+}
+
+fn solve_memoized(stones : &Stones, blinks: u8) -> u64 {
+    population::<u64>(stones).count_after(blinks as usize, Strategy::Memoized).expect("Too many stones")
 }
 
 #[aoc(day11, part1)]
@@ -63,7 +58,7 @@ fn part1(stones : &Stones) -> u64 {
 
 #[aoc(day11, part2)]
 fn part2(stones : &Stones) -> u64 {
-    solve(stones, 75)
+    solve_memoized(stones, 75)
 }
 
 
@@ -93,4 +88,45 @@ mod tests {
         assert_eq!(solve(&stones, 75), 65601038650482);
     }
 
+    #[test]
+    fn memoized() {
+        let stones = input_generator(SAMPLE);
+        assert_eq!(solve_memoized(&stones, 6), 22);
+        assert_eq!(solve_memoized(&stones, 75), 65601038650482);
+    }
+
+    #[test]
+    fn history() {
+        let mut stones: StonePopulation = population(&input_generator(SAMPLE));
+        stones.evolve(6).unwrap();
+        assert_eq!(stones.history(), &[2, 3, 4, 5, 9, 13, 22]);
+    }
+
+    #[test]
+    fn overflow() {
+        let stones = input_generator(SAMPLE);
+        let Err(Overflow::Count { generation }) = solve_checked(&stones, 200) else { panic!() };
+        let big = solve_big(&stones, generation).unwrap();
+        assert!(big > BigUint::from(u64::MAX));
+        assert_eq!(solve_big(&stones, generation - 1).unwrap(), BigUint::from(solve_checked(&stones, generation - 1).unwrap()));
+        assert!(matches!(solve_checked(&vec![u64::MAX / 10], 1), Err(Overflow::Value { generation: 1 })));
+    }
+
+    #[test]
+    fn checkpoints() {
+        let mut stones: StonePopulation<BigUint> = population(&input_generator(SAMPLE)).with_checkpoints(25);
+        assert_eq!(stones.count_at(75).unwrap(), BigUint::from(65601038650482u64));
+        assert_eq!(stones.count_at(25).unwrap(), BigUint::from(55312u64));
+        let at_30: BigUint = stones.population_at(30).unwrap().values().sum();
+        assert_eq!(at_30, stones.history()[30]);
+    }
+
+    #[test]
+    fn generated_stones() {
+        for seed in 0..10 {
+            let stones = input_generator(&crate::gen::stones(seed, 8).input);
+            assert_eq!(part1(&stones), solve_memoized(&stones, 25));
+            assert_eq!(solve_big(&stones, 40).unwrap(), solve_checked(&stones, 40).unwrap().into());
+        }
+    }
 }
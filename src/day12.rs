@@ -1,8 +1,4 @@
-use std::collections::{HashMap, HashSet};
-
-use itertools::Itertools;
-
-use crate::{grid::Grid, point::Point};
+use crate::grid::{Grid, Region};
 
 
 #[aoc_generator(day12)]
@@ -10,120 +6,21 @@ fn input_generator(input: &str) -> Grid {
     Grid::new(input)
 }
 
-type Region = HashSet<Point>;
-
-// Iterate all contiguous regions of each plant type
-fn split_regions(rgn: &Region) -> Vec<Region> {
-    let mut cells = rgn.clone();
-    let mut regions: Vec<Region> = Vec::new();
-    while !cells.is_empty() {
-        let mut rgn = HashSet::<Point>::from_iter(cells.iter().take(1).copied());
-        loop {
-            let territory:HashSet<_> = rgn.iter()
-                .flat_map(|p| p.neighbors_straight())
-                .filter(|p| cells.contains(p))
-                .collect();
-
-            // println!("{}:  ter={:?}", t, territory);
-            // println!("    rgn={:?}", rgn);
-            // println!("    nei={:?}", rgn.iter().next().unwrap().neighbors());
-
-            let before = rgn.len();
-            rgn.extend(territory);
-            if before == rgn.len() {
-                break;
-            }
-        }
-        cells.retain(|p| !rgn.contains(p));
-        regions.push(rgn);
-    }
-    regions
-}
-
-// Iterate all contiguous regions of each plant type
-fn regions(grid: &Grid) -> HashMap<char, Vec<Region>> {
-    let types = grid.map.keys().copied().collect::<Vec<_>>();
-    types.iter().map(|t| {
-        (*t, split_regions(&grid.map[t]))
-    }).collect()
-}
-
-
-fn perimeter(rgn: &Region) -> usize {
-    rgn.iter()
-        .flat_map(|p| p.neighbors_straight())
-        .filter(|p| !rgn.contains(p))
-        .count()
-}
-fn sides(rgn: &Region) -> usize {
-    let dirs = [Point::new(0,-1), Point::new(1,0), Point::new(0,1), Point::new(-1,0)];
-
-    // Find all neighbor cells that are not in the region, paired with the direction of their assocation to our cells
-    // Note this will find some cells four times.  For example,
-    //  +---+---+---+
-    //  | A | A | A |
-    //  +---+-^-+---+
-    //  | A < O > A |
-    //  +---+-v-+---+
-    //  | A | A | A |
-    //  +---+---+---+
-    //
-    // For this region of A cells, the O cell will have four associations with A, one for each side.
-    // And the Xs here complete the set of neighbors we will find.
-    //
-    //  +---+---+---+---+---+
-    //  |   | X | X | X |   |
-    //  +---+-v-+-v-+-v-+---+
-    //  | X > A | A | A < X |
-    //  +---+---+-^-+---+---+
-    //  | X > A < O > A < X |
-    //  +---+---+-v-+---+---+
-    //  | X > A | A | A < X |
-    //  +---+-^-+-^-+-^-+---+
-    //  |   | X | X | X |   |
-    //  +---+---+---+---+---+
-    let mut foreign_neighbors: HashSet<(Point, Point)> = rgn.iter()
-        .flat_map(|p| dirs.iter().map(|d| (*d, *d + *p)))
-        .filter(|(_, p)| !rgn.contains(p))
-        .collect();
-    foreign_neighbors.retain(|(_, p)| !rgn.contains(p));
-
-    // Now we must collect the contiguous sets of foreign neighbors which have the same direction-assocation with our set.
-
-    let neighbor_sets: HashMap<Point, Region> = foreign_neighbors.iter()
-        .copied()
-        .into_grouping_map()
-        .collect();
-
-    // Find all the contiguous edges from the sets of similar foreign neighbors
-    neighbor_sets.values()
-        .map(|p| split_regions(p).len())
-        .sum()
-}
-
-fn area(rgn: &Region) -> usize {
-    rgn.len()
-}
-
 fn price(rgn: &Region) -> usize {
-    area(rgn) * perimeter(rgn)
+    rgn.area() * rgn.perimeter
 }
 
 fn solve1(grid: &Grid) -> usize {
-    regions(grid).iter()
-        .flat_map(|(_, regions)| {
-            regions.iter().map(move |rgn| {
-                price(rgn)})
-        }).sum::<usize>()
+    grid.regions().iter()
+        .map(price)
+        .sum::<usize>()
 }
 
 
 fn solve2(grid: &Grid) -> usize {
-    regions(grid).iter()
-        .flat_map(|(_, regions)| {
-            regions.iter().map(move |rgn| {
-                area(rgn) * sides(rgn)})
-        }).sum::<usize>()
+    grid.regions().iter()
+        .map(|rgn| rgn.area() * rgn.sides)
+        .sum::<usize>()
 }
 
 #[aoc(day12, part1)]
@@ -214,5 +111,27 @@ EEEEE";
         // assert_eq!(part2(&input_generator(SAMPLE)), 1928);
     }
 
+    const SAMPLE24: &str =
+"AAAAAA
+AAABBA
+AAABBA
+ABBAAA
+ABBAAA
+AAAAAA";
+
+    #[test]
+    fn sample24() {
+        let grid = input_generator(SAMPLE24);
+        assert_eq!(part2(&grid), 368);
+        assert_eq!(part2(&input_generator(SAMPLE3)), 1206);
+    }
 
+    #[test]
+    fn generated_gardens() {
+        for seed in 0..10 {
+            let garden = crate::gen::garden(seed, 20, 14);
+            let grid = input_generator(&garden.input);
+            assert_eq!((part1(&grid), part2(&grid)), garden.answer);
+        }
+    }
 }
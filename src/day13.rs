@@ -134,6 +134,14 @@ Prize: X=18641, Y=10279")), 480 );
     fn part2_example() {
         assert_eq!(part2(&parse("<EXAMPLE>")), 1234 );
     }
+
+    #[test]
+    fn generated_machines() {
+        for seed in 0..10 {
+            let claws = crate::gen::claw_machines(seed, 20);
+            assert_eq!(part1(&parse(&claws.input)), claws.answer);
+        }
+    }
 }
 
 // 73458657399094
\ No newline at end of file
@@ -1,6 +1,7 @@
-use std::path::Path;
+use std::{collections::HashMap, path::Path};
 
 use aoc_runner_derive::{aoc, aoc_generator};
+use num::Integer;
 
 use crate::point::Point;
 use image;
@@ -148,15 +149,96 @@ fn sim2(game: &Game, height: usize, width: usize) -> usize {
 
 }
 
+/// Robots walking in straight lines on a field that wraps around at the edges
+#[derive(Clone, Debug)]
+pub struct RobotField {
+    robots: Game,
+    width: usize,
+    height: usize,
+}
+
+impl RobotField {
+    pub fn new(robots: &Game, width: usize, height: usize) -> RobotField {
+        RobotField { robots: robots.clone(), width, height }
+    }
+
+    /// Every robot is back where it started after this many seconds
+    pub fn period(&self) -> usize {
+        self.width.lcm(&self.height)
+    }
+
+    /// Where each robot is after t seconds
+    pub fn at(&self, t: usize) -> Vec<Point> {
+        let t = (t % self.period()) as i32;
+        self.robots.iter()
+            .map(|(pos, vel)| (*pos + *vel * t).wrap_to_grid(self.width, self.height))
+            .collect()
+    }
+
+    /// How many robots are on each occupied cell after t seconds
+    pub fn density(&self, t: usize) -> HashMap<Point, usize> {
+        let mut cells = HashMap::new();
+        for p in self.at(t) {
+            *cells.entry(p).or_insert(0) += 1;
+        }
+        cells
+    }
+
+    /// Robots in each quadrant after t seconds: top left, top right, bottom left, bottom right.
+    /// Robots on the middle row or column aren't in any.
+    pub fn quadrants(&self, t: usize) -> [usize; 4] {
+        let (w, h) = ((self.width / 2) as i32, (self.height / 2) as i32);
+        let mut quadrants = [0; 4];
+        for p in self.at(t) {
+            if p.x == w && self.width % 2 == 1 || p.y == h && self.height % 2 == 1 {
+                continue;
+            }
+            let right = p.x >= w + (self.width % 2) as i32;
+            let bottom = p.y >= h + (self.height % 2) as i32;
+            quadrants[bottom as usize * 2 + right as usize] += 1;
+        }
+        quadrants
+    }
+
+    pub fn safety_factor(&self, t: usize) -> usize {
+        self.quadrants(t).iter().product()
+    }
+
+    fn first_time(&self, test: impl Fn(&HashMap<Point, usize>) -> bool) -> Option<usize> {
+        (0..self.period()).find(|t| test(&self.density(*t)))
+    }
+
+    /// The first time two robots share a cell, if they ever do
+    pub fn first_coincidence(&self) -> Option<usize> {
+        self.first_time(|cells| cells.len() < self.robots.len())
+    }
+
+    /// The first time every robot has a cell to itself, if that ever happens
+    pub fn first_distinct(&self) -> Option<usize> {
+        self.first_time(|cells| cells.len() == self.robots.len())
+    }
+}
+
 #[aoc(day14, part1)]
 fn part1(game: &Game) -> Answer {
+    RobotField::new(game, 101, 103).safety_factor(100)
+}
+
+// The tree shows up when the robots spread out so that none of them overlap
+#[aoc(day14, part2)]
+fn part2(game: &Game) -> Answer {
+    RobotField::new(game, 101, 103).first_distinct().expect("Robots always overlap")
+}
+
+#[aoc(day14, part1, sim)]
+fn part1_sim(game: &Game) -> Answer {
 
     sim(game, 103, 101)
 
 }
 
-#[aoc(day14, part2)]
-fn part2(game: &Game) -> Answer {
+#[aoc(day14, part2, unique_rows)]
+fn part2_unique_rows(game: &Game) -> Answer {
     sim2(game, 103, 101)
 }
 
@@ -183,4 +265,49 @@ p=9,5 v=-3,-3";
         let game = parse(SAMPLE);
         assert_eq!(sim(&game, 7, 11), 12);
     }
+
+    #[test]
+    fn field_example() {
+        let field = RobotField::new(&parse(SAMPLE), 11, 7);
+        assert_eq!(field.safety_factor(100), 12);
+        assert_eq!(field.quadrants(100), [1, 3, 4, 1]);
+        assert_eq!(field.period(), 77);
+        assert_eq!(field.at(field.period() + 5), field.at(5));
+        assert_eq!(field.density(100)[&Point::new(6, 0)], 2);
+        assert_eq!(field.density(100).values().sum::<usize>(), 12);
+
+        // Two robots start on (3,0)
+        assert_eq!(field.first_coincidence(), Some(0));
+        let t = field.first_distinct().unwrap();
+        assert_eq!(field.density(t).len(), 12);
+        assert!((0..t).all(|t| field.density(t).len() < 12));
+    }
+
+    #[test]
+    fn coincidence() {
+        let field = RobotField::new(&parse("p=0,0 v=1,0
+p=3,0 v=-1,0"), 5, 1);
+        assert_eq!(field.period(), 5);
+        assert_eq!(field.first_distinct(), Some(0));
+        assert_eq!(field.first_coincidence(), Some(4));
+        assert_eq!(field.quadrants(0), [0, 0, 0, 0]);
+
+        // Even sized fields have no middle row or column
+        let field = RobotField::new(&parse("p=0,0 v=0,0
+p=1,1 v=0,0
+p=2,2 v=0,0
+p=3,3 v=0,0"), 4, 4);
+        assert_eq!(field.quadrants(0), [2, 0, 0, 2]);
+        assert_eq!(field.first_coincidence(), None);
+    }
+
+    #[test]
+    fn generated_robots() {
+        for seed in 0..10 {
+            let robots = crate::gen::robots(seed, 50, 11, 7);
+            assert_eq!(RobotField::new(&parse(&robots.input), 11, 7).safety_factor(100), robots.answer);
+            let robots = crate::gen::robots(seed, 500, 101, 103);
+            assert_eq!(part1(&parse(&robots.input)), robots.answer);
+        }
+    }
 }
@@ -1,7 +1,7 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::{all, any};
+use itertools::{all, any, Itertools};
 
 use crate::{grid::GroupMap, point::Point};
 
@@ -78,8 +78,8 @@ fn play_moves(map: &GroupMap, moves: &[char]) -> GroupMap {
     map
 }
 
-#[aoc(day15, part1)]
-fn part1(game: &Game) -> Answer {
+#[aoc(day15, part1, group_map)]
+fn part1_group_map(game: &Game) -> Answer {
     let map = play_moves(&game.0, &game.1);
     gps_score(&map, 'O')
 }
@@ -111,14 +111,542 @@ fn double_wide(map: &GroupMap) -> GroupMap {
     map
 }
 
-#[aoc(day15, part2)]
-fn part2(game: &Game) -> Answer {
+#[aoc(day15, part2, group_map)]
+fn part2_group_map(game: &Game) -> Answer {
 
     let map = double_wide(&game.0);
     let map = play_moves(&map, &game.1);
     gps_score(&map, '[')
 }
 
+pub type BoxId = usize;
+
+pub fn direction(m: char) -> Option<Point> {
+    match m {
+        '^' => Some(Point::new(0, 1)),
+        'v' => Some(Point::new(0, -1)),
+        '<' => Some(Point::new(-1, 0)),
+        '>' => Some(Point::new(1, 0)),
+        _ => None,
+    }
+}
+
+/// A box of any shape: the cells it covers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Crate {
+    pub id: BoxId,
+    pub cells: Vec<Point>,
+}
+
+impl Crate {
+    /// The top row's leftmost cell, which is what the GPS measures for the puzzle's boxes
+    pub fn anchor(&self) -> Point {
+        *self.cells.iter().min_by_key(|p| (-p.y, p.x)).unwrap()
+    }
+}
+
+/// What happens when a push runs into another robot
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Collision {
+    /// Robots are as solid as walls
+    #[default]
+    Block,
+    /// Robots get shoved along like boxes
+    Shove,
+}
+
+/// Everything that moves along with a robot's step
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Push {
+    pub boxes: Vec<BoxId>,
+    /// Other robots shoved out of the way
+    pub robots: Vec<usize>,
+}
+
+/// A warehouse of walls and boxes that robots push around.  Boxes can have any shape; pushing one
+/// pushes every box it runs into, and so on, and nothing moves if any of them hits a wall.
+/// Robots are numbered in reading order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warehouse {
+    pub width: i32,
+    pub height: i32,
+    walls: HashSet<Point>,
+    boxes: Vec<Crate>,
+    at: HashMap<Point, BoxId>,
+    robots: Vec<Point>,
+    collision: Collision,
+}
+
+impl Warehouse {
+    /// Every input cell becomes `widen` cells side by side.  `O` is a box, `[]` is a box two cells wide,
+    /// and touching cells with the same lowercase letter make up one box of any shape.
+    /// Panics if there's no robot or boxes overlap.
+    pub fn new(map: &GroupMap, widen: i32) -> Warehouse {
+        Warehouse::try_new(map, widen).expect("No robot in the warehouse, or boxes overlap")
+    }
+
+    /// Same as new, but None if there's no robot or boxes overlap
+    pub fn try_new(map: &GroupMap, widen: i32) -> Option<Warehouse> {
+        assert!(widen > 0);
+        let wide = |p: &Point| (0..widen).map(|i| Point::new(p.x * widen + i, p.y)).collect::<Vec<_>>();
+        let cells = |c: char| map.map.get(&c).into_iter().flatten();
+
+        let mut warehouse = Warehouse {
+            width: map.width * widen,
+            height: map.height,
+            walls: cells('#').flat_map(wide).collect(),
+            boxes: Vec::new(),
+            at: HashMap::new(),
+            robots: Vec::new(),
+            collision: Collision::default(),
+        };
+        let mut robots: Vec<Point> = cells('@').map(|p| Point::new(p.x * widen, p.y)).collect();
+        if robots.is_empty() {
+            return None;
+        }
+        robots.sort_by_key(|p| (-p.y, p.x));
+        warehouse.robots = robots;
+
+        let mut sources: Vec<Vec<Point>> = Vec::new();
+        sources.extend(cells('O').map(|p| vec![*p]));
+        sources.extend(cells('[').map(|p| vec![*p, *p + Point::new(1, 0)]));
+        for c in map.map.keys().filter(|c| c.is_ascii_lowercase()) {
+            let mut left: HashSet<Point> = map.map[c].clone();
+            while let Some(start) = left.iter().next().copied() {
+                left.remove(&start);
+                let mut shape = vec![start];
+                let mut i = 0;
+                while i < shape.len() {
+                    for n in shape[i].neighbors_straight() {
+                        if left.remove(&n) {
+                            shape.push(n);
+                        }
+                    }
+                    i += 1;
+                }
+                sources.push(shape);
+            }
+        }
+        // Keep ids stable from run to run
+        sources.sort_by_key(|shape| shape.iter().map(|p| (-p.y, p.x)).min());
+        for shape in sources {
+            let cells = shape.iter().flat_map(wide).collect::<Vec<_>>();
+            warehouse.add_box(&cells)?;
+        }
+        Some(warehouse)
+    }
+
+    pub fn with_collision(mut self, collision: Collision) -> Warehouse {
+        self.collision = collision;
+        self
+    }
+
+    /// Add a box covering the given cells, unless something is already there
+    pub fn add_box(&mut self, cells: &[Point]) -> Option<BoxId> {
+        if cells.is_empty() || cells.iter().any(|p| !self.is_free(p)) {
+            return None;
+        }
+        let id = self.boxes.len();
+        self.at.extend(cells.iter().map(|p| (*p, id)));
+        self.boxes.push(Crate { id, cells: cells.to_vec() });
+        Some(id)
+    }
+
+    fn is_free(&self, p: &Point) -> bool {
+        self.in_bounds(p) && !self.walls.contains(p) && !self.at.contains_key(p) && !self.robots.contains(p)
+    }
+
+    fn in_bounds(&self, p: &Point) -> bool {
+        p.x >= 0 && p.x < self.width && p.y >= 0 && p.y < self.height
+    }
+
+    /// The first robot
+    pub fn robot(&self) -> Point {
+        self.robots[0]
+    }
+
+    pub fn robots(&self) -> &[Point] {
+        &self.robots
+    }
+
+    pub fn boxes(&self) -> &[Crate] {
+        &self.boxes
+    }
+
+    pub fn box_at(&self, p: &Point) -> Option<&Crate> {
+        self.at.get(p).map(|id| &self.boxes[*id])
+    }
+
+    /// Whatever would move if the given robot stepped in `dir`, or None if it's stuck
+    pub fn pushed(&self, robot: usize, dir: Point) -> Option<Push> {
+        let mut push = Push::default();
+        let mut seen = HashSet::new();
+        let mut blocking = VecDeque::from([self.robots[robot] + dir]);
+        while let Some(p) = blocking.pop_front() {
+            if self.walls.contains(&p) || !self.in_bounds(&p) {
+                return None;
+            }
+            if let Some(other) = self.robots.iter().position(|r| *r == p).filter(|other| *other != robot) {
+                match self.collision {
+                    Collision::Block => return None,
+                    Collision::Shove => if !push.robots.contains(&other) {
+                        push.robots.push(other);
+                        blocking.push_back(p + dir);
+                    },
+                }
+            }
+            let Some(id) = self.at.get(&p).copied() else { continue };
+            if seen.insert(id) {
+                push.boxes.push(id);
+                blocking.extend(self.boxes[id].cells.iter()
+                    .map(|c| *c + dir)
+                    .filter(|n| self.at.get(n) != Some(&id)));
+            }
+        }
+        Some(push)
+    }
+
+    // Move the robot and everything it pushes
+    fn apply(&mut self, robot: usize, push: &Push, dir: Point) {
+        self.shift(&push.boxes, dir);
+        for r in push.robots.iter().chain([&robot]) {
+            self.robots[*r] = self.robots[*r] + dir;
+        }
+    }
+
+    fn shift(&mut self, moving: &[BoxId], dir: Point) {
+        for id in moving {
+            for p in self.boxes[*id].cells.iter() {
+                self.at.remove(p);
+            }
+        }
+        for id in moving {
+            for p in self.boxes[*id].cells.iter_mut() {
+                *p = *p + dir;
+                self.at.insert(*p, *id);
+            }
+        }
+    }
+
+    /// Try to move one robot one step.  False if it's blocked.
+    pub fn step_robot(&mut self, robot: usize, dir: Point) -> bool {
+        match self.pushed(robot, dir) {
+            Some(push) => {
+                self.apply(robot, &push, dir);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Try to move the first robot one step
+    pub fn step(&mut self, dir: Point) -> bool {
+        self.step_robot(0, dir)
+    }
+
+    /// Follow a list of moves with the first robot, ignoring anything that isn't an arrow
+    pub fn run(&mut self, moves: &[char]) {
+        for dir in moves.iter().filter_map(|m| direction(*m)) {
+            self.step(dir);
+        }
+    }
+
+    /// Robots take turns, each making the next move from its own list, until every list runs out.
+    /// If there are moves for a robot that isn't in the warehouse, nothing moves and the error is that robot's number.
+    pub fn run_turns(&mut self, moves: &[Vec<char>]) -> Result<(), usize> {
+        if let Some(missing) = (self.robots.len()..moves.len()).find(|r| !moves[*r].is_empty()) {
+            return Err(missing);
+        }
+        let moves: Vec<Vec<Point>> = moves.iter()
+            .take(self.robots.len())
+            .map(|list| list.iter().filter_map(|m| direction(*m)).collect())
+            .collect();
+        let turns = moves.iter().map(|list| list.len()).max().unwrap_or(0);
+        for turn in 0..turns {
+            for (robot, list) in moves.iter().enumerate() {
+                if let Some(dir) = list.get(turn) {
+                    self.step_robot(robot, *dir);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// GPS coordinate of a point: 100 times its distance from the top edge plus its distance from the left
+    pub fn gps_at(&self, p: &Point) -> usize {
+        ((self.height - 1 - p.y) * 100 + p.x) as usize
+    }
+
+    /// Sum of GPS coordinates of each box, measured at whichever cell `anchor` picks
+    pub fn gps_by(&self, anchor: impl Fn(&Crate) -> Point) -> usize {
+        self.boxes.iter().map(|b| self.gps_at(&anchor(b))).sum()
+    }
+
+    pub fn gps(&self) -> usize {
+        self.gps_by(Crate::anchor)
+    }
+}
+
+impl core::fmt::Display for Warehouse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                let p = Point::new(x, y);
+                let c = if self.walls.contains(&p) {
+                    '#'
+                } else if self.robots.contains(&p) {
+                    '@'
+                } else if let Some(b) = self.box_at(&p) {
+                    match b.cells.len() {
+                        1 => 'O',
+                        2 if b.cells.contains(&(p + Point::new(1, 0))) && b.anchor() == p => '[',
+                        2 if b.cells.contains(&(p - Point::new(1, 0))) => ']',
+                        _ => (b'a' + (b.id % 26) as u8) as char,
+                    }
+                } else {
+                    '.'
+                };
+                write!(f, "{}", c)?;
+            }
+            if y > 0 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What happened on one move
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveEvent {
+    pub dir: char,
+    /// Where the robot is after the move
+    pub robot: Point,
+    pub accepted: bool,
+    pub moved: Vec<BoxId>,
+    /// GPS total after the move
+    pub gps: usize,
+}
+
+impl MoveEvent {
+    /// Box ids depend on the implementation, so two events agree if everything else does
+    pub fn agrees(&self, other: &MoveEvent) -> bool {
+        (self.dir, self.robot, self.accepted, self.gps) == (other.dir, other.robot, other.accepted, other.gps)
+    }
+}
+
+/// One event per line: direction, robot x,y, moved or blocked, GPS, and the ids of the boxes moved
+pub fn export_log(log: &[MoveEvent]) -> String {
+    log.iter()
+        .map(|e| format!("{} {},{} {} {} {}",
+            e.dir, e.robot.x, e.robot.y,
+            if e.accepted { "moved" } else { "blocked" },
+            e.gps,
+            e.moved.iter().join(",")).trim_end().to_string())
+        .join("\n")
+}
+
+pub fn import_log(input: &str) -> Option<Vec<MoveEvent>> {
+    input.lines().map(|line| {
+        let mut fields = line.split_whitespace();
+        let dir = fields.next()?.chars().next()?;
+        let (x, y) = fields.next()?.split_once(',')?;
+        let robot = Point::new(x.parse().ok()?, y.parse().ok()?);
+        let accepted = match fields.next()? {
+            "moved" => true,
+            "blocked" => false,
+            _ => return None,
+        };
+        let gps = fields.next()?.parse().ok()?;
+        let moved = match fields.next() {
+            Some(ids) => ids.split(',').map(|id| id.parse().ok()).collect::<Option<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        Some(MoveEvent { dir, robot, accepted, moved, gps })
+    }).collect()
+}
+
+/// The index of the first move where two logs disagree, if any.  A log that stops early disagrees there.
+pub fn first_divergence(a: &[MoveEvent], b: &[MoveEvent]) -> Option<usize> {
+    (0..a.len().max(b.len())).find(|i| match (a.get(*i), b.get(*i)) {
+        (Some(a), Some(b)) => !a.agrees(b),
+        _ => true,
+    })
+}
+
+/// A warehouse that remembers every move of its first robot, so they can be undone, redone, exported and replayed
+#[derive(Clone, Debug)]
+pub struct Recording {
+    warehouse: Warehouse,
+    log: Vec<MoveEvent>,
+    undone: Vec<MoveEvent>,
+
+    // Robots shoved on each logged move, which the log itself doesn't keep
+    shoved: Vec<Vec<usize>>,
+}
+
+impl Recording {
+    pub fn new(warehouse: Warehouse) -> Recording {
+        Recording { warehouse, log: Vec::new(), undone: Vec::new(), shoved: Vec::new() }
+    }
+
+    /// Replay the directions from an exported log on a fresh warehouse
+    pub fn replay(warehouse: Warehouse, log: &[MoveEvent]) -> Recording {
+        let mut recording = Recording::new(warehouse);
+        recording.run(&log.iter().map(|e| e.dir).collect::<Vec<_>>());
+        recording
+    }
+
+    pub fn warehouse(&self) -> &Warehouse {
+        &self.warehouse
+    }
+
+    pub fn log(&self) -> &[MoveEvent] {
+        &self.log
+    }
+
+    fn apply(&mut self, m: char) -> Option<MoveEvent> {
+        let dir = direction(m)?;
+        let push = self.warehouse.pushed(0, dir);
+        let accepted = push.is_some();
+        let push = push.unwrap_or_default();
+        if accepted {
+            self.warehouse.apply(0, &push, dir);
+        }
+        self.shoved.push(push.robots);
+        Some(MoveEvent {
+            dir: m,
+            robot: self.warehouse.robot(),
+            accepted,
+            moved: push.boxes,
+            gps: self.warehouse.gps(),
+        })
+    }
+
+    /// Make a move and log it.  Anything that isn't an arrow is ignored.
+    pub fn play(&mut self, m: char) -> Option<&MoveEvent> {
+        let event = self.apply(m)?;
+        self.undone.clear();
+        self.log.push(event);
+        self.log.last()
+    }
+
+    pub fn run(&mut self, moves: &[char]) {
+        for m in moves {
+            self.play(*m);
+        }
+    }
+
+    /// Take back the last move.  False if there's nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(event) = self.log.pop() else { return false };
+        let robots = self.shoved.pop().unwrap();
+        if event.accepted {
+            let back = Point::new(0, 0) - direction(event.dir).unwrap();
+            self.warehouse.apply(0, &Push { boxes: event.moved.clone(), robots }, back);
+        }
+        self.undone.push(event);
+        true
+    }
+
+    /// Make the last undone move again.  False if there's nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(undone) = self.undone.pop() else { return false };
+        let event = self.apply(undone.dir).unwrap();
+        self.log.push(event);
+        true
+    }
+}
+
+/// Like play_moves, but logging every move.  This version doesn't know about box ids.
+pub fn play_moves_logged(map: &GroupMap, moves: &[char], cell: char) -> Vec<MoveEvent> {
+    let mut map = map.clone();
+    let mut pos = *map.map[&'@'].iter().next().unwrap();
+    let mut log = Vec::new();
+    for m in moves {
+        let Some(dir) = direction(*m) else { continue };
+        let accepted = push(&mut map, &[pos], dir);
+        if accepted {
+            pos = pos + dir;
+        }
+        log.push(MoveEvent { dir: *m, robot: pos, accepted, moved: Vec::new(), gps: gps_score(&map, cell) });
+    }
+    log
+}
+
+/// Moves for each robot.  A line can start with `N:` to give moves to robot N; lines without one carry
+/// on with the list before, starting with robot 0.
+pub fn parse_robot_moves(input: &str) -> Vec<Vec<char>> {
+    let mut lists: Vec<Vec<char>> = Vec::new();
+    let mut robot = 0;
+    for line in input.lines() {
+        let moves = match line.split_once(':') {
+            Some((n, moves)) if n.trim().parse::<usize>().is_ok() => {
+                robot = n.trim().parse().unwrap();
+                moves
+            }
+            _ => line,
+        };
+        if lists.len() <= robot {
+            lists.resize(robot + 1, Vec::new());
+        }
+        lists[robot].extend(moves.chars().filter(|c| direction(*c).is_some()));
+    }
+    lists
+}
+
+/// Many warehouses, each a map followed by its moves, all separated by blank lines.
+/// A map can have no moves at all.  Moves that come before any map are a None entry.
+pub fn parse_batch(input: &str) -> Vec<Option<(GroupMap, Vec<Vec<char>>)>> {
+    let lines: Vec<&str> = input.lines().collect();
+    let mut batch: Vec<Option<(GroupMap, Vec<Vec<char>>)>> = Vec::new();
+    for block in lines.split(|l| l.trim().is_empty()).filter(|b| !b.is_empty()) {
+        let block = block.join("\n");
+        if block.trim_start().starts_with('#') {
+            batch.push(Some((GroupMap::new(&block), Vec::new())));
+        } else if let Some(last) = batch.last_mut() {
+            // More moves for a stray entry are just as stray
+            let Some((_, moves)) = last else { continue };
+            for (robot, list) in parse_robot_moves(&block).into_iter().enumerate() {
+                if moves.len() <= robot {
+                    moves.resize(robot + 1, Vec::new());
+                }
+                moves[robot].extend(list);
+            }
+        } else {
+            batch.push(None);
+        }
+    }
+    batch
+}
+
+/// GPS total of each warehouse in a batch after its robots take their turns, or None for moves
+/// with no map, a warehouse without a robot, or moves for a robot it doesn't have
+pub fn batch_gps(input: &str, widen: i32, collision: Collision) -> Vec<Option<usize>> {
+    parse_batch(input).iter()
+        .map(|entry| {
+            let (map, moves) = entry.as_ref()?;
+            let mut warehouse = Warehouse::try_new(map, widen)?.with_collision(collision);
+            warehouse.run_turns(moves).ok()?;
+            Some(warehouse.gps())
+        })
+        .collect()
+}
+
+#[aoc(day15, part1)]
+fn part1(game: &Game) -> Answer {
+    let mut warehouse = Warehouse::new(&game.0, 1);
+    warehouse.run(&game.1);
+    warehouse.gps()
+}
+
+#[aoc(day15, part2)]
+fn part2(game: &Game) -> Answer {
+    let mut warehouse = Warehouse::new(&game.0, 2);
+    warehouse.run(&game.1);
+    warehouse.gps()
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -178,4 +706,218 @@ v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
         assert_eq!(part2(&parse(SAMPLE2)), 618);
         assert_eq!(part2(&parse(SAMPLE)), 9021);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn group_map_example() {
+        assert_eq!(part1_group_map(&parse(SAMPLE)), 10092);
+        assert_eq!(part2_group_map(&parse(SAMPLE)), 9021);
+    }
+
+    #[test]
+    fn widened_render() {
+        let (map, moves) = parse(SAMPLE);
+        let mut warehouse = Warehouse::new(&map, 2);
+        assert_eq!(warehouse.to_string().lines().nth(4), Some("##....[]@.....[]..##"));
+        warehouse.run(&moves);
+        assert_eq!(warehouse.to_string(), "####################
+##[].......[].[][]##
+##[]...........[].##
+##[]........[][][]##
+##[]......[]....[]##
+##..##......[]....##
+##..[]............##
+##..@......[].[][]##
+##......[][]..[]..##
+####################");
+    }
+
+    #[test]
+    fn shaped_boxes() {
+        // An L shaped box resting on a plain one
+        let map = GroupMap::new("#######
+#.....#
+#.aa..#
+#.aO..#
+#.@...#
+#######");
+        let mut warehouse = Warehouse::new(&map, 1);
+        assert_eq!(warehouse.boxes().len(), 2);
+        let l = warehouse.box_at(&Point::new(2, 3)).unwrap().clone();
+        assert_eq!(l.cells.len(), 3);
+        assert_eq!(l.anchor(), Point::new(2, 3));
+
+        // Pushing up moves only the L; the O is beside its foot
+        assert!(warehouse.step(direction('^').unwrap()));
+        assert_eq!(warehouse.to_string(), "#######
+#.aa..#
+#.a...#
+#.@O..#
+#.....#
+#######");
+        // Now it's against the wall
+        assert!(!warehouse.step(direction('^').unwrap()));
+
+        // Go around and push the O up under the L's arm
+        warehouse.run(&"v>^".chars().collect::<Vec<_>>());
+        assert_eq!(warehouse.robot(), Point::new(3, 2));
+        assert!(!warehouse.step(direction('^').unwrap()));
+        assert_eq!(warehouse.gps(), 102 + 203);
+        assert_eq!(warehouse.gps_by(|b| *b.cells.iter().min_by_key(|p| (p.y, p.x)).unwrap()), 202 + 203);
+    }
+
+    #[test]
+    fn wider_boxes() {
+        let (map, _) = parse(STARTER);
+        let mut warehouse = Warehouse::new(&map, 3);
+        assert_eq!(warehouse.width, 24);
+        assert!(warehouse.boxes().iter().all(|b| b.cells.len() == 3));
+        assert_eq!(warehouse.to_string().lines().nth(1), Some("###......aaa...bbb...###"));
+        warehouse.run(&">>".chars().collect::<Vec<_>>());
+        assert_eq!(warehouse.robot(), Point::new(8, 5));
+    }
+
+    #[test]
+    fn recording() {
+        let (map, moves) = parse(STARTER);
+        let mut recording = Recording::new(Warehouse::new(&map, 1));
+        recording.run(&moves);
+        assert_eq!(recording.log().len(), moves.len());
+        assert_eq!(recording.log().last().unwrap().gps, 2028);
+        assert_eq!(recording.log().iter().filter(|e| !e.accepted).count(), 5);
+        let end = recording.warehouse().clone();
+
+        // Undo everything, then redo it all
+        let start = Warehouse::new(&map, 1);
+        while recording.undo() {}
+        assert_eq!(recording.warehouse(), &start);
+        assert!(recording.log().is_empty());
+        while recording.redo() {}
+        assert_eq!(recording.warehouse(), &end);
+
+        // A new move forgets what was undone
+        recording.undo();
+        recording.play('<');
+        assert!(!recording.redo());
+    }
+
+    #[test]
+    fn export_and_diff() {
+        let (map, moves) = parse(SAMPLE);
+        let mut recording = Recording::new(Warehouse::new(&map, 2));
+        recording.run(&moves);
+        let exported = export_log(recording.log());
+        let imported = import_log(&exported).unwrap();
+        assert_eq!(imported, recording.log());
+
+        let replayed = Recording::replay(Warehouse::new(&map, 2), &imported);
+        assert_eq!(replayed.log(), recording.log());
+
+        // The old implementation agrees move by move
+        let legacy = play_moves_logged(&double_wide(&map), &moves, '[');
+        assert_eq!(first_divergence(&legacy, recording.log()), None);
+
+        // Until it doesn't
+        let mut broken = legacy.clone();
+        broken[100].gps += 1;
+        assert_eq!(first_divergence(&broken, recording.log()), Some(100));
+        assert_eq!(first_divergence(&legacy[..50], recording.log()), Some(50));
+        assert_eq!(import_log("> 1,2 sideways 3"), None);
+    }
+
+    #[test]
+    fn robots_take_turns() {
+        let map = GroupMap::new("########
+#@.O...#
+#......#
+#...O.@#
+########");
+        let warehouse = Warehouse::new(&map, 1);
+        assert_eq!(warehouse.robots(), &[Point::new(1, 3), Point::new(6, 1)]);
+
+        // Robot 0 pushes its box right while robot 1 pushes left, until they meet
+        let moves = vec![">>>>".chars().collect(), "<<<<".chars().collect()];
+        let mut blocking = warehouse.clone();
+        blocking.run_turns(&moves).unwrap();
+        assert_eq!(blocking.to_string(), "########
+#....@O#
+#......#
+#O@....#
+########");
+
+        // Robots in each other's way
+        let map = GroupMap::new("#######
+#@..@.#
+#######");
+        let moves = vec![">>>>".chars().collect(), vec![]];
+        let mut blocking = Warehouse::new(&map, 1);
+        blocking.run_turns(&moves).unwrap();
+        assert_eq!(blocking.robots(), &[Point::new(3, 1), Point::new(4, 1)]);
+        let mut shoving = Warehouse::new(&map, 1).with_collision(Collision::Shove);
+        shoving.run_turns(&moves).unwrap();
+        assert_eq!(shoving.robots(), &[Point::new(4, 1), Point::new(5, 1)]);
+    }
+
+    #[test]
+    fn needs_a_robot() {
+        let batch = "####\n#.O#\n####\n\n<<\n\n####\n#@O#\n####";
+        assert_eq!(batch_gps(batch, 1, Collision::Block), vec![None, Some(102)]);
+        assert!(Warehouse::try_new(&GroupMap::new("####\n#.O#\n####"), 1).is_none());
+        // A two-wide box can't run into a box beside it
+        assert!(Warehouse::try_new(&GroupMap::new("#####\n#@[O#\n#####"), 1).is_none());
+    }
+
+    #[test]
+    fn moves_before_any_map() {
+        let batch = "<<\n\n>>\n\n####\n#@.O#\n####\n\n>";
+        let parsed = parse_batch(batch);
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed[0].is_none());
+        assert_eq!(parsed[1].as_ref().unwrap().1, vec![vec!['>']]);
+        assert_eq!(batch_gps(batch, 1, Collision::Block), vec![None, Some(103)]);
+    }
+
+    #[test]
+    fn undo_shoves() {
+        let map = GroupMap::new("#######
+#@O@..#
+#######");
+        let start = Warehouse::new(&map, 1).with_collision(Collision::Shove);
+        let mut recording = Recording::new(start.clone());
+        recording.run(&['>', '>', '>']);
+        assert_eq!(recording.warehouse().robots(), &[Point::new(3, 1), Point::new(5, 1)]);
+        assert!(!recording.log()[2].accepted);
+        while recording.undo() {}
+        assert_eq!(recording.warehouse(), &start);
+    }
+
+    #[test]
+    fn batches() {
+        let batch = format!("{}\n\n{}\n\n\n{}\n\n#####\n#@.O#\n#####\n", STARTER, SAMPLE, SAMPLE2);
+        let parsed = parse_batch(&batch);
+        assert_eq!(parsed.len(), 4);
+        assert!(parsed[3].as_ref().unwrap().1.is_empty());
+        assert_eq!(batch_gps(&batch, 1, Collision::Block)[..2], [Some(2028), Some(10092)]);
+        assert_eq!(batch_gps(&batch, 2, Collision::Block)[1..3], [Some(9021), Some(618)]);
+        assert_eq!(batch_gps(&batch, 1, Collision::Block)[3], Some(103));
+
+        // Moves for a second robot that isn't there
+        let stray = "#####\n#@.O#\n#####\n\n<\n1: >";
+        assert_eq!(batch_gps(stray, 1, Collision::Block), vec![None]);
+        let mut warehouse = Warehouse::new(&parse_batch(stray)[0].as_ref().unwrap().0, 1);
+        assert_eq!(warehouse.run_turns(&[vec!['>'], vec![], vec!['<']]), Err(2));
+        assert_eq!(warehouse.robot(), Point::new(1, 1));
+        assert_eq!(warehouse.run_turns(&[vec!['>'], vec![]]), Ok(()));
+        assert_eq!(warehouse.robot(), Point::new(2, 1));
+
+        assert_eq!(parse_robot_moves("<<\n1: >>\nv\n0:^"), vec![vec!['<', '<', '^'], vec!['>', '>', 'v']]);
+    }
+
+    #[test]
+    fn generated_warehouses() {
+        for seed in 0..10 {
+            let game = parse(&crate::gen::warehouse(seed, 12, 9, 15, 300).input);
+            assert_eq!(part1(&game), part1_group_map(&game));
+            assert_eq!(part2(&game), part2_group_map(&game));
+        }
+    }
+}
@@ -321,6 +321,15 @@ mod tests {
         assert_eq!(part2(&parse(SAMPLE)), 45);
         assert_eq!(part2(&parse(SAMPLE2)), 64);
     }
+
+    #[test]
+    fn generated_mazes() {
+        for seed in 0..10 {
+            let maze = crate::gen::reindeer_maze(seed, 6 + seed as usize % 3, 5);
+            let game = parse(&maze.input);
+            assert_eq!((part1(&game), part2(&game)), maze.answer, "{}", maze.input);
+        }
+    }
 }
 
 // too high: 142556
@@ -175,6 +175,7 @@ fn part2(orig: &Game) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::harness::{shrink_u64, Harness};
 
     const SAMPLE: &str = "Register A: 729
 Register B: 0
@@ -233,4 +234,21 @@ Code: 4,0", "") // would set register B to 44354.
         let comp = parse("Register A: 2024\nRegister B: 0\nRegister C: 0\n\nProgram: 0,3,5,4,3,0");
         assert_eq!(part2(&comp), "117440");
     }
+
+    // The program precompiled_program was written from
+    const PROGRAM: [u64; 16] = [2, 4, 1, 5, 7, 5, 4, 3, 1, 6, 0, 3, 5, 5, 3, 0];
+
+    #[test]
+    fn implementations_agree() {
+        let harness = Harness::new()
+            .register("computer", |a: &u64| {
+                let mut comp = Computer::new([*a, 0, 0], PROGRAM.to_vec());
+                comp.run();
+                comp.output
+            })
+            .register("precompiled", |a: &u64| precompiled_program(*a));
+        // The computer always prints at least once, so start from 1
+        let shrink = |a: &u64| shrink_u64(*a).into_iter().filter(|a| *a > 0).collect();
+        harness.check(17, 500, |rng| 1 + (rng.next_u64() >> rng.below(64)), shrink).unwrap();
+    }
 }
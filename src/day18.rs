@@ -1,7 +1,6 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use pathfinding::prelude::astar;
 use std::collections::{HashMap, HashSet};
-use crate::point::Point;
+use crate::{disjoint_set::DisjointSet, point::Point};
 
 type Grid = Vec<Point>;
 #[aoc_generator(day18)]
@@ -13,20 +12,49 @@ fn parse(input: &str) -> Grid {
         .collect()
 }
 
+/// The memory space we walk through, and where we walk from and to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Space {
+    pub width: i32,
+    pub height: i32,
+    pub start: Point,
+    pub goal: Point,
+}
+
+impl Space {
+    /// A square space crossed from the top left to the bottom right corner
+    pub fn square(size: i32) -> Space {
+        Space { width: size, height: size, start: Point::new(0, 0), goal: Point::new(size - 1, size - 1) }
+    }
+
+    pub fn with_start(self, start: Point) -> Space {
+        Space { start, ..self }
+    }
+
+    pub fn with_goal(self, goal: Point) -> Space {
+        Space { goal, ..self }
+    }
+
+    pub fn valid(&self, p: &Point) -> bool {
+        p.x >= 0 && p.x < self.width && p.y >= 0 && p.y < self.height
+    }
+}
+
+// Walk back from pos to the start, then return the route from the start to pos, inclusive
 fn path(origins: &HashMap<Point, Point>, pos: &Point) -> Vec<Point> {
-    let mut path = Vec::new();
+    let mut path = vec![*pos];
     let mut pos = pos;
-    while let Some(prev) = origins.get(&pos) {
-        path.push(pos);
+    while let Some(prev) = origins.get(pos) {
+        path.push(*prev);
         pos = prev;
     }
     path.reverse();
-    path.iter().map(|p| *pos).collect()
+    path
 }
 
-fn print(input: &[Point], path: &[Point], size: i32) {
-    for y in 0..size {
-        for x in 0..size {
+fn print(input: &[Point], path: &[Point], space: &Space) {
+    for y in 0..space.height {
+        for x in 0..space.width {
             let pos = Point::new(x,y);
             if input.contains(&pos) {
                 print!("#");
@@ -40,59 +68,105 @@ fn print(input: &[Point], path: &[Point], size: i32) {
     }
 }
 
-fn my_astar(input: &[Point], size: i32, start: Point, end: Point) -> usize {
-
+fn my_astar(input: &[Point], space: &Space) -> Option<Vec<Point>> {
+    let (start, end) = (space.start, space.goal);
     let grid: HashSet<Point> = HashSet::from_iter(input.iter().cloned());
+    if grid.contains(&start) || grid.contains(&end) {
+        return None;
+    }
 
     let dirs = [Point::new(1, 0), Point::new(0, 1), Point::new(-1, 0), Point::new(0, -1)];
     let mut open: std::collections::BTreeSet<(usize,Point)> = std::collections::BTreeSet::new();
     let mut came_from = HashMap::new();
 
     let mut g_score = HashMap::new();
-    let mut f_score = HashMap::new();
 
     g_score.insert(start, 0usize);
-    f_score.insert(start, start.manhattan_distance(&end) as usize);
     open.insert((start.manhattan_distance(&end) as usize, start));
 
-    while !open.is_empty() {
-        let (_, cur) = open.pop_first().unwrap();
-
+    while let Some((_, cur)) = open.pop_first() {
         if cur == end {
-            // print(input, &path(&came_from, &end), size);
-            return path(&came_from, &end).len();
+            return Some(path(&came_from, &end));
         }
         for pos in dirs.iter()
                 .map(|d| cur + *d)
                 .filter(|p| !grid.contains(p))
-                .filter(|p| p.x >= 0 && p.x < size  && p.y >= 0 && p.y < size )
+                .filter(|p| space.valid(p))
                 {
             let score = g_score[&cur] + 1;
             if score < *g_score.get(&pos).unwrap_or(&usize::MAX) {
                 came_from.insert(pos, cur);
                 g_score.insert(pos, score);
-                f_score.insert(pos, score + pos.manhattan_distance(&end) as usize);
                 open.insert((score + pos.manhattan_distance(&end) as usize, pos));
             }
         }
     }
-    usize::MAX
+    None
+}
+
+/// The shortest route through the fallen bytes, from start to goal inclusive
+pub fn route(bytes: &[Point], space: &Space) -> Option<Vec<Point>> {
+    // unexpectedly, my A* function is 8 x faster than the pathfinding crate
+    my_astar(bytes, space)
+}
+
+/// Number of steps in the shortest route, or usize::MAX if there isn't one
+pub fn solve_in(bytes: &[Point], space: &Space) -> usize {
+    route(bytes, space).map_or(usize::MAX, |r| r.len() - 1)
 }
 
-// unexpectedly, my A* function is 8 x faster than the pathfinding crate
 fn solve(bytes: &[Point], size: i32) -> usize {
-    let start = Point::new(0,0);
-    let end = Point::new(size-1,size-1);
+    solve_in(bytes, &Space::square(size))
+}
+
+/// Shortest route when `fallen` bytes are down before we start and one more falls with every step we take.
+/// We may wait in place, but may never stand where a byte has fallen.  The route includes any waits,
+/// so it is one point longer than the number of steps.  None if we can't get there within `budget` steps.
+pub fn route_while_falling(bytes: &[Point], fallen: usize, space: &Space, budget: usize) -> Option<Vec<Point>> {
+    // When did each cell become corrupted?
+    let mut corrupt_at: HashMap<Point, usize> = HashMap::new();
+    for (i, b) in bytes.iter().enumerate() {
+        corrupt_at.entry(*b).or_insert((i + 1).saturating_sub(fallen));
+    }
+    let free_at = |p: &Point, t: usize| space.valid(p) && corrupt_at.get(p).is_none_or(|c| *c > t);
+
+    if !free_at(&space.start, 0) {
+        return None;
+    }
 
-    // if let Some((_, cost)) =  astar(&start,
-    //         |p| p.neighbors_straight().iter()
-    //                                 .filter(|p| !bytes.contains(p))
-    //                                 .filter(|p| p.x >= 0 && p.x < size  && p.y >= 0 && p.y < size )
-    //                                 .map(|p| (*p, 1))
-    //                                 .collect::<Vec<_>>(),
-    //         |p| p.manhattan_distance(&end),
-    //          |p| *p == end) { cost as usize } else { usize::MAX }
-    my_astar(bytes, size, start, end)
+    // came_from[t] tells where we were at time t-1 for each place we can be at time t
+    let mut came_from: Vec<HashMap<Point, Point>> = vec![HashMap::from([(space.start, space.start)])];
+    for t in 0..budget {
+        let frontier = &came_from[t];
+        if frontier.contains_key(&space.goal) {
+            break;
+        }
+        let mut next = HashMap::new();
+        for p in frontier.keys() {
+            for n in p.neighbors_straight().into_iter().chain([*p]) {
+                if free_at(&n, t + 1) {
+                    next.entry(n).or_insert(*p);
+                }
+            }
+        }
+        // Nothing changes once all the bytes are down, so if the frontier stops growing we're stuck
+        let settled = t + fallen >= bytes.len() && next.len() == frontier.len();
+        if next.is_empty() || (settled && !next.contains_key(&space.goal)) {
+            return None;
+        }
+        came_from.push(next);
+    }
+
+    let last = came_from.len() - 1;
+    if !came_from[last].contains_key(&space.goal) {
+        return None;
+    }
+    let mut route = vec![space.goal];
+    for t in (1..=last).rev() {
+        route.push(came_from[t][route.last().unwrap()]);
+    }
+    route.reverse();
+    Some(route)
 }
 
 #[aoc(day18, part1)]
@@ -102,15 +176,19 @@ fn part1(input: &Grid) -> usize {
     solve(&input[..bytes], size)
 }
 
-fn solve2(input: &Grid, first: usize, size: i32) -> Point {
+fn solve2_in(input: &Grid, first: usize, space: &Space) -> Point {
     let indexes = (first+1..input.len()).collect::<Vec<_>>();
-    let found = first + indexes.partition_point(|i| solve(&input[..*i], size) < usize::MAX-1);
+    let found = first + indexes.partition_point(|i| solve_in(&input[..*i], space) < usize::MAX-1);
     if found < input.len() {
         return input[found];
     }
     unreachable!()
 }
 
+fn solve2(input: &Grid, first: usize, size: i32) -> Point {
+    solve2_in(input, first, &Space::square(size))
+}
+
 #[aoc(day18, part2)]
 fn part2(input: &Grid) -> Point {
     let size = 71;
@@ -118,6 +196,50 @@ fn part2(input: &Grid) -> Point {
     solve2(input, bytes, size)
 }
 
+// Drop every byte, then lift them off again in reverse order, joining each freed cell to its open neighbors.
+// The byte whose removal first connects start to end is the one that blocked the path.
+fn solve2_union_find(input: &Grid, space: &Space) -> Point {
+    let (start, end) = (space.start, space.goal);
+    let blocked: HashSet<Point> = input.iter().copied().collect();
+
+    let mut open = DisjointSet::new();
+    let free = |open: &mut DisjointSet<Point>, p: Point| {
+        open.insert(&p);
+        for n in p.neighbors_straight() {
+            if open.contains(&n) {
+                open.union(&p, &n);
+            }
+        }
+    };
+
+    for x in 0..space.width {
+        for y in 0..space.height {
+            let p = Point::new(x, y);
+            if !blocked.contains(&p) {
+                free(&mut open, p);
+            }
+        }
+    }
+    assert!(!open.same(&start, &end), "The path is never blocked");
+
+    // A byte may fall on the same cell twice; it is only free once we lift the earliest one
+    let first_drop: HashMap<Point, usize> = input.iter().enumerate().rev().map(|(i, p)| (*p, i)).collect();
+    for (i, byte) in input.iter().enumerate().rev() {
+        if first_drop[byte] == i {
+            free(&mut open, *byte);
+            if open.same(&start, &end) {
+                return *byte;
+            }
+        }
+    }
+    unreachable!()
+}
+
+#[aoc(day18, part2, union_find)]
+fn part2_union_find(input: &Grid) -> Point {
+    solve2_union_find(input, &Space::square(71))
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -158,4 +280,66 @@ mod tests {
     fn part2_example() {
         assert_eq!(solve2(&parse(SAMPLE), 12, 7), Point::new(6,1));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn part2_union_find_example() {
+        assert_eq!(solve2_union_find(&parse(SAMPLE), &Space::square(7)), Point::new(6,1));
+    }
+
+    fn check_route(route: &[Point], bytes: &[Point], space: &Space) {
+        assert_eq!(route[0], space.start);
+        assert_eq!(*route.last().unwrap(), space.goal);
+        assert!(route.iter().all(|p| space.valid(p) && !bytes.contains(p)));
+        assert!(route.windows(2).all(|w| w[0].is_adjacent_straight(&w[1])));
+    }
+
+    #[test]
+    fn route_example() {
+        let bytes = parse(SAMPLE);
+        let space = Space::square(7);
+        let route = route(&bytes[..12], &space).unwrap();
+        assert_eq!(route.len(), 23);
+        check_route(&route, &bytes[..12], &space);
+
+        let space = Space { width: 9, height: 7, ..space }.with_start(Point::new(8, 0)).with_goal(Point::new(0, 4));
+        let route = super::route(&bytes[..12], &space).unwrap();
+        check_route(&route, &bytes[..12], &space);
+        assert_eq!(solve_in(&bytes[..12], &space), route.len() - 1);
+        assert_eq!(solve_in(&bytes, &Space::square(7)), usize::MAX);
+    }
+
+    #[test]
+    fn route_while_falling_example() {
+        let bytes = parse(SAMPLE);
+        let space = Space::square(7);
+        for fallen in [0, 5] {
+            let route = route_while_falling(&bytes, fallen, &space, 100).unwrap();
+            assert!(route.len() > solve_in(&bytes[..fallen], &space));
+            for (t, p) in route.iter().enumerate() {
+                assert!(!bytes[..(fallen + t).min(bytes.len())].contains(p), "stood on a byte at {}", t);
+            }
+            assert!(route.windows(2).all(|w| w[0] == w[1] || w[0].is_adjacent_straight(&w[1])));
+        }
+        // The way is cut off by the 21st byte, before we can get across
+        assert_eq!(route_while_falling(&bytes, 12, &space, 100), None);
+        // Nothing falls in time to get in our way on an empty floor
+        assert_eq!(route_while_falling(&[], 0, &space, 100).unwrap().len(), 13);
+        assert_eq!(route_while_falling(&bytes, 0, &space, 11), None);
+        // Everything is down before we start, and the way is blocked
+        assert_eq!(route_while_falling(&bytes, bytes.len(), &space, 100), None);
+    }
+
+    #[test]
+    fn generated_bytes() {
+        for seed in 0..10 {
+            let size = 7 + 2 * (seed as usize % 4);
+            let falling = crate::gen::falling_bytes(seed, size);
+            let (fallen, steps, cut) = falling.answer;
+            let bytes = parse(&falling.input);
+            let space = Space::square(size as i32);
+            assert_eq!(solve_in(&bytes[..fallen], &space), steps);
+            assert_eq!(solve2_in(&bytes, fallen, &space), cut);
+            assert_eq!(solve2_union_find(&bytes, &space), cut);
+        }
+    }
+}
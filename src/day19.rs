@@ -1,6 +1,7 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::Itertools;
 
 type Towels = HashSet<String>;
 type Game = (Towels, Vec<String>);
@@ -89,14 +90,309 @@ fn solve_dp(input: &Game) -> usize {
     patterns.iter().map(|p| dp2(towels, p)).sum()
 }
 
+/// Finds every towel in a design in a single left-to-right pass, using an Aho-Corasick automaton
+/// over all the towels.  Towels may be any length.
+pub struct TowelMatcher {
+    nodes: Vec<Node>,
+}
+
+#[derive(Default)]
+struct Node {
+    next: HashMap<u8, usize>,
+
+    // Longest proper suffix of this node's prefix that is also in the trie
+    fail: usize,
+
+    // Length of the towel ending here, if any
+    towel: Option<usize>,
+
+    // Nearest node along the fail chain that ends a towel
+    output: Option<usize>,
+}
+
+impl TowelMatcher {
+    pub fn new<'a>(towels: impl IntoIterator<Item = &'a str>) -> TowelMatcher {
+        let mut nodes = vec![Node::default()];
+        for towel in towels.into_iter().filter(|t| !t.is_empty()) {
+            let mut n = 0;
+            for c in towel.bytes() {
+                n = match nodes[n].next.get(&c) {
+                    Some(next) => *next,
+                    None => {
+                        nodes.push(Node::default());
+                        let next = nodes.len() - 1;
+                        nodes[n].next.insert(c, next);
+                        next
+                    }
+                };
+            }
+            nodes[n].towel = Some(towel.len());
+        }
+
+        // Breadth first, so every node's fail target is finished before we need it
+        let mut queue: VecDeque<usize> = nodes[0].next.values().copied().collect();
+        while let Some(n) = queue.pop_front() {
+            let edges: Vec<(u8, usize)> = nodes[n].next.iter().map(|(c, m)| (*c, *m)).collect();
+            for (c, m) in edges {
+                let mut f = nodes[n].fail;
+                let fail = loop {
+                    if let Some(next) = nodes[f].next.get(&c) {
+                        break *next;
+                    }
+                    if f == 0 {
+                        break 0;
+                    }
+                    f = nodes[f].fail;
+                };
+                nodes[m].fail = fail;
+                nodes[m].output = if nodes[fail].towel.is_some() { Some(fail) } else { nodes[fail].output };
+                queue.push_back(m);
+            }
+        }
+        TowelMatcher { nodes }
+    }
+
+    fn step(&self, mut n: usize, c: u8) -> usize {
+        loop {
+            if let Some(next) = self.nodes[n].next.get(&c) {
+                return *next;
+            }
+            if n == 0 {
+                return 0;
+            }
+            n = self.nodes[n].fail;
+        }
+    }
+
+    /// For each position in the design, the lengths of all the towels that start there
+    pub fn starts(&self, design: &str) -> Vec<Vec<usize>> {
+        let mut starts = vec![Vec::new(); design.len()];
+        let mut n = 0;
+        for (i, c) in design.bytes().enumerate() {
+            n = self.step(n, c);
+            let mut out = if self.nodes[n].towel.is_some() { Some(n) } else { self.nodes[n].output };
+            while let Some(m) = out {
+                let len = self.nodes[m].towel.unwrap();
+                starts[i + 1 - len].push(len);
+                out = self.nodes[m].output;
+            }
+        }
+        starts
+    }
+
+    // ways[i] is the number of ways to finish the design from position i
+    fn ways(&self, starts: &[Vec<usize>]) -> Vec<usize> {
+        let mut ways = vec![0usize; starts.len() + 1];
+        ways[starts.len()] = 1;
+        for i in (0..starts.len()).rev() {
+            ways[i] = starts[i].iter().map(|len| ways[i + len]).sum();
+        }
+        ways
+    }
+
+    /// Number of different ways to make the design
+    pub fn count(&self, design: &str) -> usize {
+        self.ways(&self.starts(design))[0]
+    }
+
+    pub fn possible(&self, design: &str) -> bool {
+        self.count(design) > 0
+    }
+
+    /// Every way to make the design, produced lazily in lexical order of towel lengths
+    pub fn arrangements<'a>(&self, design: &'a str) -> Arrangements<'a> {
+        let mut starts = self.starts(design);
+        let ways = self.ways(&starts);
+        // Only keep towels that lead somewhere, so we never walk into a dead end
+        for (i, lens) in starts.iter_mut().enumerate() {
+            lens.retain(|len| ways[i + len] > 0);
+            lens.sort();
+        }
+        let stack = if ways[0] > 0 { vec![(0, 0)] } else { Vec::new() };
+        Arrangements { design, starts, stack }
+    }
+
+    /// prefix_ways[i] is the number of ways to make the first i characters of the design
+    pub fn prefix_ways(&self, design: &str) -> Vec<usize> {
+        let starts = self.starts(design);
+        let mut ways = vec![0usize; design.len() + 1];
+        ways[0] = 1;
+        for i in 0..design.len() {
+            for len in starts[i].iter() {
+                ways[i + len] += ways[i];
+            }
+        }
+        ways
+    }
+
+    // How far into the design can we match part of some towel, starting from position i?
+    fn reach(&self, design: &str, i: usize) -> usize {
+        let mut n = 0;
+        for (j, c) in design.bytes().enumerate().skip(i) {
+            match self.nodes[n].next.get(&c) {
+                Some(next) => n = *next,
+                None => return j,
+            }
+        }
+        design.len()
+    }
+
+    /// Everything we know about how to make a design, listing at most `cap` arrangements
+    pub fn explain<'a>(&self, design: &'a str, cap: usize) -> Explanation<'a> {
+        let prefix_ways = self.prefix_ways(design);
+        let count = prefix_ways[design.len()];
+        let longest_prefix = (0..=design.len()).rev().find(|i| prefix_ways[*i] > 0).unwrap();
+        let fails_at = (count == 0).then(|| {
+            (0..=longest_prefix)
+                .filter(|i| prefix_ways[*i] > 0)
+                .map(|i| self.reach(design, i))
+                .max()
+                .unwrap()
+        });
+        Explanation {
+            design,
+            arrangements: self.arrangements(design).take(cap).collect(),
+            count,
+            prefix_ways,
+            longest_prefix,
+            fails_at,
+        }
+    }
+
+    /// A way to make the design from as few towels as possible
+    pub fn fewest<'a>(&self, design: &'a str) -> Option<Vec<&'a str>> {
+        let starts = self.starts(design);
+        // best[i] is the fewest towels covering design[..i], and the length of the last one
+        let mut best: Vec<Option<(usize, usize)>> = vec![None; design.len() + 1];
+        best[0] = Some((0, 0));
+        for i in 0..design.len() {
+            let Some((count, _)) = best[i] else { continue };
+            for len in starts[i].iter() {
+                if best[i + len].is_none_or(|(c, _)| count + 1 < c) {
+                    best[i + len] = Some((count + 1, *len));
+                }
+            }
+        }
+        best[design.len()]?;
+        let mut towels = Vec::new();
+        let mut end = design.len();
+        while end > 0 {
+            let (_, len) = best[end].unwrap();
+            towels.push(&design[end - len..end]);
+            end -= len;
+        }
+        towels.reverse();
+        Some(towels)
+    }
+}
+
+/// How a design can be made from towels, or where it goes wrong
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation<'a> {
+    pub design: &'a str,
+
+    /// Some of the ways to make the design, up to the requested cap
+    pub arrangements: Vec<Vec<&'a str>>,
+
+    /// Number of ways to make the whole design
+    pub count: usize,
+
+    /// prefix_ways[i] is the number of ways to make the first i characters
+    pub prefix_ways: Vec<usize>,
+
+    /// Length of the longest prefix we can make
+    pub longest_prefix: usize,
+
+    /// For impossible designs, the first character that no towel from a reachable position can match
+    pub fails_at: Option<usize>,
+}
+
+impl core::fmt::Display for Explanation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.fails_at {
+            None => {
+                writeln!(f, "{}: {} ways", self.design, self.count)?;
+                for towels in self.arrangements.iter() {
+                    writeln!(f, "  {}", towels.join(" "))?;
+                }
+                if self.arrangements.len() < self.count {
+                    writeln!(f, "  ...")?;
+                }
+            }
+            Some(at) => {
+                writeln!(f, "{}: impossible", self.design)?;
+                writeln!(f, "  longest prefix: {} ({} of {})", &self.design[..self.longest_prefix], self.longest_prefix, self.design.len())?;
+                match self.design[at..].chars().next() {
+                    Some(c) => writeln!(f, "  fails at {}: '{}'", at, c)?,
+                    None => writeln!(f, "  fails at {}: ran out of design", at)?,
+                }
+            }
+        }
+        write!(f, "  ways by prefix: {}", self.prefix_ways.iter().join(" "))
+    }
+}
+
+/// Iterator over the ways to make a design from towels
+pub struct Arrangements<'a> {
+    design: &'a str,
+
+    // Towel lengths that can start at each position and still finish the design
+    starts: Vec<Vec<usize>>,
+
+    // Depth-first search state: (position, index of the next towel to try there)
+    stack: Vec<(usize, usize)>,
+}
+
+impl<'a> Iterator for Arrangements<'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((pos, choice)) = self.stack.last().copied() {
+            if pos == self.design.len() {
+                // Report the towels on the stack, then backtrack
+                let towels = self.stack.windows(2)
+                    .map(|w| &self.design[w[0].0..w[1].0])
+                    .collect();
+                self.stack.pop();
+                return Some(towels);
+            }
+            if choice == self.starts[pos].len() {
+                self.stack.pop();
+                continue;
+            }
+            self.stack.last_mut().unwrap().1 += 1;
+            self.stack.push((pos + self.starts[pos][choice], 0));
+        }
+        None
+    }
+}
+
+fn matcher(towels: &Towels) -> TowelMatcher {
+    TowelMatcher::new(towels.iter().map(|t| t.as_str()))
+}
+
 #[aoc(day19, part1)]
 fn part1(input: &Game) -> Answer {
+    let (towels, patterns) = input;
+    let matcher = matcher(towels);
+    patterns.iter().filter(|p| matcher.possible(p)).count()
+}
+
+#[aoc(day19, part1, recursive)]
+fn part1_recursive(input: &Game) -> Answer {
     let (towels, patterns) = input;
     patterns.iter().map(|p| if test_possible(towels, p) {1} else {0}).sum()
 }
 
 #[aoc(day19, part2)]
 fn part2(input: &Game) -> Answer {
+    let (towels, patterns) = input;
+    let matcher = matcher(towels);
+    patterns.iter().map(|p| matcher.count(p)).sum()
+}
+
+#[aoc(day19, part2, dp)]
+fn part2_dp(input: &Game) -> Answer {
     solve_dp(input)
     // solve_recursive(input)
 }
@@ -105,6 +401,7 @@ fn part2(input: &Game) -> Answer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::harness::{shrink_string, shrink_vec, Harness, Rng};
 
     const SAMPLE: &str = "r, wr, b, g, bwu, rb, gb, br
 
@@ -126,4 +423,121 @@ bbrgwb";
     fn part2_example() {
         assert_eq!(part2(&parse(SAMPLE)), 16);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn part1_recursive_example() {
+        assert_eq!(part1_recursive(&parse(SAMPLE)), 6);
+        assert_eq!(part2_dp(&parse(SAMPLE)), 16);
+    }
+
+    #[test]
+    fn matcher_arrangements() {
+        let (towels, _) = parse(SAMPLE);
+        let matcher = matcher(&towels);
+        let mut ways: Vec<_> = matcher.arrangements("gbbr").collect();
+        ways.sort();
+        assert_eq!(ways, vec![vec!["g", "b", "b", "r"], vec!["g", "b", "br"], vec!["gb", "b", "r"], vec!["gb", "br"]]);
+        assert_eq!(matcher.arrangements("rrbgbr").count(), 6);
+        assert_eq!(matcher.arrangements("ubwu").next(), None);
+        assert_eq!(matcher.fewest("rrbgbr").unwrap().len(), 4);
+        assert_eq!(matcher.fewest("brwrr"), Some(vec!["br", "wr", "r"]));
+        assert_eq!(matcher.fewest("bbrgwb"), None);
+    }
+
+    #[test]
+    fn matcher_long_towels() {
+        // Towels longer than any fixed limit, and towels that overlap each other's suffixes
+        let long = "w".repeat(20);
+        let matcher = TowelMatcher::new([long.as_str(), "ww", "uwwu", "wu"]);
+        let design = format!("u{}u", long);
+        assert_eq!(matcher.count(&long), 1 + 1);
+        assert_eq!(matcher.count(&design), 0);
+        assert_eq!(matcher.count(&format!("{}wu", long)), 1 + 1);
+        assert_eq!(matcher.fewest(&long).unwrap().len(), 1);
+        assert_eq!(matcher.count("uwwuww"), 1);
+    }
+
+    #[test]
+    fn matcher_many_towels() {
+        let towels: Vec<String> = (0..2000).map(|i| format!("{:b}", i + 2)[1..].to_string()).collect();
+        let matcher = TowelMatcher::new(towels.iter().map(|t| t.as_str()));
+        let design = "0110100110010110".repeat(4);
+        let towels: Towels = towels.into_iter().collect();
+        assert_eq!(matcher.count(&design), dp(&towels, &design));
+    }
+
+    #[test]
+    fn explain_possible() {
+        let (towels, _) = parse(SAMPLE);
+        let matcher = matcher(&towels);
+        let explanation = matcher.explain("gbbr", 2);
+        assert_eq!(explanation.count, 4);
+        assert_eq!(explanation.arrangements.len(), 2);
+        assert_eq!(explanation.prefix_ways, vec![1, 1, 2, 2, 4]);
+        assert_eq!(explanation.longest_prefix, 4);
+        assert_eq!(explanation.fails_at, None);
+        assert_eq!(format!("{}", explanation), "gbbr: 4 ways\n  g b b r\n  g b br\n  ...\n  ways by prefix: 1 1 2 2 4");
+    }
+
+    #[test]
+    fn explain_impossible() {
+        let (towels, _) = parse(SAMPLE);
+        let matcher = matcher(&towels);
+        let explanation = matcher.explain("bbrgwb", 10);
+        assert_eq!(explanation.count, 0);
+        assert!(explanation.arrangements.is_empty());
+        assert_eq!(explanation.longest_prefix, 4);
+        assert_eq!(explanation.fails_at, Some(5));
+        assert_eq!(format!("{}", explanation), "bbrgwb: impossible\n  longest prefix: bbrg (4 of 6)\n  fails at 5: 'b'\n  ways by prefix: 1 1 1 2 2 0 0");
+
+        let explanation = matcher.explain("ubwu", 10);
+        assert_eq!(explanation.longest_prefix, 0);
+        assert_eq!(explanation.fails_at, Some(0));
+    }
+
+    fn random_game(rng: &mut Rng) -> Game {
+        parse(&crate::gen::towels(rng.next_u64(), rng.between(1, 12), rng.between(1, 8)).input)
+    }
+
+    fn shrink_game((towels, designs): &Game) -> Vec<Game> {
+        let mut smaller: Vec<Game> = shrink_vec(designs).into_iter()
+            .map(|d| (towels.clone(), d))
+            .collect();
+        let list: Vec<String> = towels.iter().cloned().sorted().collect();
+        smaller.extend(shrink_vec(&list).into_iter().map(|t| (t.into_iter().collect(), designs.clone())));
+        for (i, d) in designs.iter().enumerate() {
+            smaller.extend(shrink_string(d).into_iter().map(|d| {
+                let mut designs = designs.clone();
+                designs[i] = d;
+                (towels.clone(), designs)
+            }));
+        }
+        smaller.retain(|(t, d)| !t.is_empty() && d.iter().all(|d| !d.is_empty()));
+        smaller
+    }
+
+    #[test]
+    fn implementations_agree() {
+        let possible = Harness::new()
+            .register("matcher", part1)
+            .register("recursive", part1_recursive);
+        possible.check(19, 200, random_game, shrink_game).unwrap();
+
+        let counts = Harness::new()
+            .register("matcher", part2)
+            .register("recursive", solve_recursive)
+            .register("dp", |(towels, designs): &Game| designs.iter().map(|d| dp(towels, d)).sum())
+            .register("dp2", solve_dp);
+        counts.check(19, 200, random_game, shrink_game).unwrap();
+    }
+
+    #[test]
+    fn generated_designs() {
+        for seed in 0..10 {
+            let towels = crate::gen::towels(seed, 12, 30);
+            let game = parse(&towels.input);
+            assert_eq!(part1(&game), towels.answer);
+            assert_eq!(part1_recursive(&game), towels.answer);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
@@ -13,47 +13,91 @@ fn parse(input: &str) -> Game {
 
 }
 
-fn simple_path(grid: &Game, start: Point, end: Point) -> Vec<Point> {
-    let mut cur = start;
-    let mut path = Vec::new();
-    let mut prev = start;
-    loop {
-        path.push(cur);
-        if cur == end {
-            return path;
+// Steps from `from` to every open cell we can reach, by breadth-first search
+fn distances(grid: &Game, walls: &HashSet<Point>, from: Point) -> HashMap<Point, usize> {
+    let mut dist = HashMap::from([(from, 0)]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(cur) = queue.pop_front() {
+        let d = dist[&cur];
+        for p in cur.neighbors_straight() {
+            if p.x >= 0 && p.x < grid.width && p.y >= 0 && p.y < grid.height && !walls.contains(&p) && !dist.contains_key(&p) {
+                dist.insert(p, d + 1);
+                queue.push_back(p);
+            }
         }
-        let inext:Vec<_> = cur.neighbors_straight().iter()
-                .cloned()
-                .filter(|p| grid.get(p) != '#')
-                .filter(|p| p.x >= 0 && p.x < grid.width  && p.y >= 0 && p.y < grid.height )
-                .filter(|p| *p != prev)
-                .collect();
-        assert_eq!(inext.len(), 1);
-        prev = cur;
-        cur = *inext.first().unwrap();
     }
+    dist
 }
 
-fn solve(grid: &Game, cheat_distance: usize, gain: usize) -> usize {
-    let start = grid.map.get(&'S').unwrap().iter().next().unwrap();
-    let end = grid.map.get(&'E').unwrap().iter().next().unwrap();
-    let path = simple_path(grid, *start, *end);
-
-    let mut all_cheats = HashSet::new();
-    for (i, pos) in path.iter().enumerate() {
-        if i > path.len() - cheat_distance - 1 {
-            break;
+/// Distances along the track from the start and to the end, for working out what a cheat saves.
+/// The track may branch and loop; we only need the shortest honest route.
+pub struct Race {
+    from_start: HashMap<Point, usize>,
+    to_end: HashMap<Point, usize>,
+    best: usize,
+}
+
+impl Race {
+    /// None if there's no S or E, or no honest way from one to the other
+    pub fn new(grid: &Game) -> Option<Race> {
+        let start = *grid.map.get(&'S')?.iter().next()?;
+        let end = *grid.map.get(&'E')?.iter().next()?;
+        let walls = grid.map.get(&'#').cloned().unwrap_or_default();
+        let from_start = distances(grid, &walls, start);
+        let best = *from_start.get(&end)?;
+        let to_end = distances(grid, &walls, end);
+        Some(Race { from_start, to_end, best })
+    }
+
+    /// Length of the shortest honest route
+    pub fn best(&self) -> usize {
+        self.best
+    }
+
+    // Time saved by cheating from a to b, if any
+    fn saving(&self, a: &Point, b: &Point) -> Option<usize> {
+        let total = self.from_start.get(a)? + a.manhattan_distance(b) as usize + self.to_end.get(b)?;
+        self.best.checked_sub(total).filter(|s| *s > 0)
+    }
+
+    /// How many cheats save each amount of time, scanning the diamond of cells within reach of every track cell
+    pub fn savings(&self, cheat_distance: usize) -> BTreeMap<usize, usize> {
+        let reach = cheat_distance as i32;
+        let mut histogram = BTreeMap::new();
+        for a in self.from_start.keys() {
+            for dx in -reach..=reach {
+                let span = reach - dx.abs();
+                for dy in -span..=span {
+                    if let Some(saving) = self.saving(a, &(*a + Point::new(dx, dy))) {
+                        *histogram.entry(saving).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        histogram
+    }
+
+    /// Same as savings, but by comparing every pair of track cells
+    pub fn savings_pairwise(&self, cheat_distance: usize) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for a in self.from_start.keys() {
+            for b in self.to_end.keys().filter(|b| a.manhattan_distance(b) as usize <= cheat_distance) {
+                if let Some(saving) = self.saving(a, b) {
+                    *histogram.entry(saving).or_insert(0) += 1;
+                }
+            }
         }
-        all_cheats.extend(
-            path[i + cheat_distance..].iter()
-                .enumerate()
-                .filter(|(_, p)| p.manhattan_distance(pos) as usize <= cheat_distance)
-                .filter(|(j, p)| *j + cheat_distance - p.manhattan_distance(pos) as usize >= gain)
-                .map(|(_, p)| (*pos, *p))
-        );
+        histogram
     }
 
-    all_cheats.len()
+    /// Number of cheats that save at least `gain`
+    pub fn count(&self, cheat_distance: usize, gain: usize) -> usize {
+        self.savings(cheat_distance).range(gain.max(1)..).map(|(_, n)| n).sum()
+    }
+}
+
+fn solve(grid: &Game, cheat_distance: usize, gain: usize) -> usize {
+    Race::new(grid).expect("No way from S to E").count(cheat_distance, gain)
 }
 
 #[aoc(day20, part1)]
@@ -99,6 +143,54 @@ mod tests {
         assert_eq!(solve(&parse(SAMPLE), 20, 73), 7);
         assert_eq!(solve(&parse(SAMPLE), 20, 50), 285);
     }
+
+    #[test]
+    fn histogram_example() {
+        let race = Race::new(&parse(SAMPLE)).unwrap();
+        assert_eq!(race.best(), 84);
+        assert_eq!(race.savings(2), BTreeMap::from([
+            (2, 14), (4, 14), (6, 2), (8, 4), (10, 2), (12, 3), (20, 1), (36, 1), (38, 1), (40, 1), (64, 1)]));
+        let big: BTreeMap<_, _> = race.savings(20).into_iter().filter(|(s, _)| *s >= 50).collect();
+        assert_eq!(big, BTreeMap::from([
+            (50, 32), (52, 31), (54, 29), (56, 39), (58, 25), (60, 23), (62, 20),
+            (64, 19), (66, 12), (68, 14), (70, 12), (72, 22), (74, 4), (76, 3)]));
+        assert_eq!(race.savings(20), race.savings_pairwise(20));
+    }
+
+    const BRANCHING: &str = "###########
+#S..#.....#
+#.#.#.###.#
+#.#...#...#
+#.#####.###
+#.......#E#
+###.###.#.#
+#.....#...#
+###########";
+
+    #[test]
+    fn branching_track() {
+        let race = Race::new(&parse(BRANCHING)).unwrap();
+        assert_eq!(race.best(), 16);
+        for cheat_distance in [2, 3, 6] {
+            assert_eq!(race.savings(cheat_distance), race.savings_pairwise(cheat_distance));
+        }
+        // The best is straight through the wall beside E
+        assert_eq!(race.savings(2), BTreeMap::from([(2, 1), (4, 1)]));
+        assert_eq!(race.count(2, 3), 1);
+
+        assert!(Race::new(&parse("#####\n#S#E#\n#####")).is_none());
+        assert!(Race::new(&parse("#####\n#S..#\n#####")).is_none());
+    }
+
+    #[test]
+    fn generated_tracks() {
+        for seed in 0..10 {
+            let track = crate::gen::racetrack(seed, 10, 7);
+            let race = Race::new(&parse(&track.input)).unwrap();
+            assert_eq!(race.best(), track.answer);
+            assert_eq!(race.savings(2), race.savings_pairwise(2));
+        }
+    }
 }
 
 // 1651 is too high
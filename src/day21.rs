@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
@@ -31,98 +31,6 @@ fn parse(input: &str) -> Game {
 //
 // From A -> Up : left
 
-struct NumberPad {
-    pos: Point,
-    panic: Point,
-}
-
-impl NumberPad {
-    fn new() -> NumberPad {
-        NumberPad {
-            pos: Point::new(2, 0),
-            panic: Point::new(0, 0),
-        }
-    }
-
-    fn locate(&self, key: char) -> Point {
-        match key {
-            'A' => Point::new(2, 0),
-            '0' => Point::new(1, 0),
-            '1' => Point::new(0, 1),
-            '2' => Point::new(1, 1),
-            '3' => Point::new(2, 1),
-            '4' => Point::new(0, 2),
-            '5' => Point::new(1, 2),
-            '6' => Point::new(2, 2),
-            '7' => Point::new(0, 3),
-            '8' => Point::new(1, 3),
-            '9' => Point::new(2, 3),
-            'X' => self.panic,
-            _ => panic!("Unknown key {}", key),
-        }
-    }
-
-    fn control_seq(&mut self, code: &str) -> Vec<String> {
-        let mut seq = Vec::new();
-        for c in code.chars() {
-            seq.push(self.press_key(c));
-        }
-        seq
-    }
-
-    fn press_key(&mut self, key: char) -> String {
-        let key = self.locate(key);
-        let travel = key - self.pos;
-
-        // Every movement ends with A
-
-        // Every path to key with fewest turns
-
-        let horz =
-            if travel.x < 0 { "<".repeat(-travel.x as usize) }
-            else if travel.x > 0 { ">".repeat(travel.x as usize) }
-            else { "".to_string() };
-
-        let vert =
-            if travel.y < 0 { "v".repeat(-travel.y as usize) }
-            else if travel.y > 0 { "^".repeat(travel.y as usize) }
-            else { "".to_string() };
-
-        // Three possibilities:
-        //    1. Not moving horizontally
-        //    2. Moving in both directions, but one path crosses panic zone
-        //         When moving up from (_,0) to (0,_) => must move vert first
-        //         When moving down from (0,_) to (_,) => must move horz first
-        //    3. Moving in both directions, but both are valid
-
-        let vert_horz = vert.clone() + &horz + &"A";
-        let horz_vert = horz.clone() + &vert + &"A";
-
-        let seq =
-            if self.pos.y == self.panic.y && key.x == self.panic.x {
-                // Danger zone: Move up then left
-                vert_horz
-            } else if self.pos.x == self.panic.x && key.y == self.panic.y {
-                // Danger zone: Move right then down
-                horz_vert
-            } else if horz_vert == vert_horz {
-                // Both paths are the same
-                horz_vert
-            } else if travel.x >= 0 {
-                vert_horz
-            } else {
-                // Both paths are safe
-                // But we only need this one:
-                horz_vert
-            };
-
-        self.pos = key;
-        seq
-    }
-}
-
-
-
 /*
     +---+---+
     | ^ | A |
@@ -131,103 +39,6 @@ impl NumberPad {
 +---+---+---+
  */
 
- #[derive(Clone)]
-struct DirPad {
-    pos: Point,
-    panic: Point,
-}
-
-impl DirPad {
-    fn new() -> DirPad {
-        DirPad {
-            pos: Point::new(2, 1),
-            panic: Point::new(0, 1),
-        }
-    }
-
-    fn locate(&self, key: char) -> Point {
-        match key {
-            'A' => Point::new(2, 1),
-            '^' => Point::new(1, 1),
-            '<' => Point::new(0, 0),
-            'v' => Point::new(1, 0),
-            '>' => Point::new(2, 0),
-            _ => panic!("Unknown key {}", key),
-        }
-    }
-
-    fn control_seq_one(&mut self, code: &str) -> Vec<String> {
-        let mut seq = Vec::new();
-        for c in code.chars() {
-            seq.push(self.press_key(c));
-        }
-        seq
-    }
-
-    // Take a desired code sequence and produce the seq needed on the dpad to create it
-    fn control_seq(&mut self, code: Vec<String>, memo: &mut HashMap<String, Vec<String>>) -> Vec<String> {
-        let mut seq = Vec::new();
-        for c in code {
-            if memo.contains_key(&c) {
-                seq.extend(memo[&c].clone());
-                continue;
-            }
-            let s = self.control_seq_one(&c);
-            memo.insert(c, s.clone());
-            seq.extend(s);
-        }
-        seq
-    }
-
-    fn press_key(&mut self, key: char) -> String {
-        let key = self.locate(key);
-        let travel = key - self.pos;
-
-        // Every path to key with fewest turns
-        let horz =
-            if travel.x < 0 { "<".repeat(-travel.x as usize) }
-            else if travel.x > 0 { ">".repeat(travel.x as usize) }
-            else { "".to_string() };
-
-        let vert =
-            if travel.y < 0 { "v".repeat(-travel.y as usize) }
-            else if travel.y > 0 { "^".repeat(travel.y as usize) }
-            else { "".to_string() };
-
-        // Three possibilities:
-        //    1. Not moving horizontally
-        //    2. Moving in both directions, but one path crosses panic zone
-        //         When moving up from (_,0) to (0,_) => must move vert first
-        //         When moving down from (0,_) to (_,) => must move horz first
-        //    3. Moving in both directions, but both are valid
-
-        let vert_horz = vert.clone() + &horz + &"A";
-        let horz_vert = horz.clone() + &vert + &"A";
-
-        let seq =
-            if self.pos.y == self.panic.y && key.x == self.panic.x {
-                // Danger zone: Move down then right
-                vert_horz
-            } else if self.pos.x == self.panic.x && key.y == self.panic.y {
-                // Danger zone: Move left then up
-                horz_vert
-            } else if horz_vert == vert_horz {
-                // Both paths are the same
-                horz_vert
-            } else if travel.x < 0 {
-                horz_vert
-            } else {
-                vert_horz
-                // Both paths are safe but vert_horz is optimal
-                // vec![horz_vert, vert_horz]
-            };
-
-        self.pos = key;
-        seq
-    }
-
-}
-
 
 fn demux(cmds: &Vec<Vec<String>>) -> HashSet<String> {
 
@@ -256,51 +67,267 @@ fn demux_basic() {
 }
 
 
-fn cull(cmds: HashSet<String>) -> HashSet<String> {
-    cmds.iter().take(1).cloned().collect()
-    // let shortest = cmds.iter().map(|s| s.len()).min().unwrap();
-    // cmds.iter().filter(|s| s.len() == shortest).cloned().collect()
+// Where each direction key moves a robot arm
+fn step(key: char) -> Point {
+    match key {
+        '^' => Point::new(0, 1),
+        'v' => Point::new(0, -1),
+        '<' => Point::new(-1, 0),
+        '>' => Point::new(1, 0),
+        _ => Point::new(0, 0),
+    }
+}
+
+/// A keypad read from a picture of its keys, one character per key, top row first.
+/// Spaces (and anything off the end of a short row) are gaps the arm must never pass over.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keypad {
+    keys: HashMap<char, Point>,
+    cells: HashMap<Point, char>,
+    width: i32,
+    height: i32,
+}
+
+impl Keypad {
+    pub fn parse(layout: &str) -> Keypad {
+        let height = layout.lines().count() as i32;
+        let width = layout.lines().map(|l| l.chars().count()).max().unwrap_or(0) as i32;
+        let mut keys = HashMap::new();
+        let mut cells = HashMap::new();
+        for (row, line) in layout.lines().enumerate() {
+            for (x, c) in line.chars().enumerate().filter(|(_, c)| *c != ' ') {
+                let pos = Point::new(x as i32, height - row as i32 - 1);
+                assert!(keys.insert(c, pos).is_none(), "Key {} appears twice", c);
+                cells.insert(pos, c);
+            }
+        }
+        Keypad { keys, cells, width, height }
+    }
+
+    /// The door's number pad
+    pub fn numeric() -> Keypad {
+        Keypad::parse("789\n456\n123\n 0A")
+    }
+
+    /// The robots' direction pad
+    pub fn directional() -> Keypad {
+        Keypad::parse(" ^A\n<v>")
+    }
+
+    pub fn locate(&self, key: char) -> Point {
+        match self.keys.get(&key) {
+            Some(pos) => *pos,
+            None => panic!("Unknown key {}", key),
+        }
+    }
+
+    pub fn contains(&self, key: char) -> bool {
+        self.keys.contains_key(&key)
+    }
+
+    /// The key under the arm at pos, if there is one
+    pub fn key_at(&self, pos: &Point) -> Option<char> {
+        self.cells.get(pos).copied()
+    }
+
+    /// All the holes in the pad, bottom row first
+    pub fn gaps(&self) -> Vec<Point> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Point::new(x, y)))
+            .filter(|p| !self.cells.contains_key(p))
+            .collect()
+    }
+
+    /// Every shortest way to move the arm from one key to another over keys only, then press it
+    pub fn moves(&self, from: char, to: char) -> Vec<String> {
+        let (from, to) = (self.locate(from), self.locate(to));
+
+        // Steps from every key to the target
+        let mut dist = HashMap::from([(to, 0)]);
+        let mut queue = VecDeque::from([to]);
+        while let Some(cur) = queue.pop_front() {
+            for p in cur.neighbors_straight() {
+                if self.cells.contains_key(&p) && !dist.contains_key(&p) {
+                    dist.insert(p, dist[&cur] + 1);
+                    queue.push_back(p);
+                }
+            }
+        }
+
+        // Walk every downhill path from the start
+        let mut out = Vec::new();
+        let mut paths = vec![(from, String::new())];
+        while let Some((pos, path)) = paths.pop() {
+            if pos == to {
+                out.push(path + "A");
+                continue;
+            }
+            for key in "^v<>".chars() {
+                let next = pos + step(key);
+                if dist.get(&next).is_some_and(|d| *d + 1 == dist[&pos]) {
+                    paths.push((next, path.clone() + &key.to_string()));
+                }
+            }
+        }
+        out.sort();
+        out
+    }
+}
+
+/// Keypads that control each other: the code is typed on the first, whose arm is driven by the second,
+/// and so on down to the last one, which the human presses directly.  Every arm starts on A.
+pub struct KeypadChain {
+    pads: Vec<Keypad>,
+
+    // Fewest human presses to make the arm on one pad press one key after another
+    costs: HashMap<(usize, char, char), usize>,
+}
+
+impl KeypadChain {
+    pub fn new(pads: Vec<Keypad>) -> KeypadChain {
+        assert!(!pads.is_empty());
+        for pad in pads.iter() {
+            assert!(pad.contains('A'), "Every arm starts on A");
+        }
+        for pad in pads[1..].iter() {
+            assert!("^v<>".chars().all(|c| pad.contains(c)), "Controlling pads need every direction");
+            assert!(pad.keys.keys().all(|c| "^v<>A".contains(*c)), "Controlling pads only have directions and A");
+        }
+        KeypadChain { pads, costs: HashMap::new() }
+    }
+
+    /// The door's number pad, driven through `robots` robots at direction pads, plus the human's own direction pad
+    pub fn standard(robots: usize) -> KeypadChain {
+        let mut pads = vec![Keypad::numeric()];
+        pads.extend(std::iter::repeat_n(Keypad::directional(), robots + 1));
+        KeypadChain::new(pads)
+    }
+
+    // Fewest human presses to press `to` on pad `level`, whose arm rests on `from`
+    fn key_cost(&mut self, level: usize, from: char, to: char) -> usize {
+        if level == self.pads.len() - 1 {
+            return 1;
+        }
+        if let Some(cost) = self.costs.get(&(level, from, to)) {
+            return *cost;
+        }
+        let cost = self.pads[level].moves(from, to).iter()
+            .map(|path| self.seq_cost(path, level + 1))
+            .min()
+            .expect("Key can't be reached");
+        self.costs.insert((level, from, to), cost);
+        cost
+    }
+
+    // Fewest human presses to type a whole sequence on pad `level`, starting from A
+    fn seq_cost(&mut self, seq: &str, level: usize) -> usize {
+        let mut from = 'A';
+        let mut cost = 0;
+        for to in seq.chars() {
+            cost += self.key_cost(level, from, to);
+            from = to;
+        }
+        cost
+    }
+
+    /// Fewest presses for the human to type the code on the first pad
+    pub fn presses(&mut self, code: &str) -> usize {
+        self.seq_cost(code, 0)
+    }
+
+    // The cheapest human sequence to type `seq` on pad `level`
+    fn expand(&mut self, seq: &str, level: usize) -> String {
+        if level == self.pads.len() - 1 {
+            return seq.to_string();
+        }
+        let mut from = 'A';
+        let mut out = String::new();
+        for to in seq.chars() {
+            let path = self.pads[level].moves(from, to).into_iter()
+                .min_by_key(|path| self.seq_cost(path, level + 1))
+                .expect("Key can't be reached");
+            out += &self.expand(&path, level + 1);
+            from = to;
+        }
+        out
+    }
+
+    /// One of the shortest sequences for the human to press.  Long chains make very long sequences.
+    pub fn sequence(&mut self, code: &str) -> String {
+        self.expand(code, 0)
+    }
+
+    /// Press the keys on the human's pad and follow them down the chain to see what gets typed on the first pad.
+    /// Fails at the first press that swings an arm over a gap, or that isn't on the human's pad at all.
+    pub fn replay(&self, presses: &str) -> Result<String, ReplayError> {
+        let mut arms: Vec<Point> = self.pads.iter().map(|pad| pad.locate('A')).collect();
+        let mut typed = String::new();
+        for (press, key) in presses.chars().enumerate() {
+            if !self.pads.last().unwrap().contains(key) {
+                return Err(ReplayError::UnknownKey { step: press, key });
+            }
+
+            // Each A passes the key under the arm one pad further down
+            let mut key = key;
+            let mut level = self.pads.len() - 1;
+            loop {
+                if level == 0 {
+                    typed.push(key);
+                    break;
+                }
+                level -= 1;
+                if key != 'A' {
+                    arms[level] = arms[level] + step(key);
+                    if self.pads[level].key_at(&arms[level]).is_none() {
+                        return Err(ReplayError::Panic(Panic { step: press, pad: level, pos: arms[level] }));
+                    }
+                    break;
+                }
+                key = self.pads[level].key_at(&arms[level]).unwrap();
+            }
+        }
+        Ok(typed)
+    }
+}
+
+/// Why a sequence of presses couldn't be replayed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    Panic(Panic),
+    /// Index and key of a press that isn't on the human's pad
+    UnknownKey { step: usize, key: char },
+}
+
+/// Where and when a robot arm left its keypad
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Panic {
+    /// Index of the human's press that caused it
+    pub step: usize,
+    /// Which pad in the chain, counting from the one the code is typed on
+    pub pad: usize,
+    /// Where the arm ended up
+    pub pos: Point,
 }
 
 // To control the position on the numberpad, we use the direction pad.
 struct RubeGoldberg {
-    pad: NumberPad,
-    dpads: Vec<DirPad>,
-    memo: HashMap<String, Vec<String>>,
+    chain: KeypadChain,
 }
 
 impl RubeGoldberg {
     fn new(robots: usize) -> RubeGoldberg {
-        let dpads = vec![DirPad::new(); robots];
-        RubeGoldberg {
-            pad: NumberPad::new(),
-            dpads,
-            memo: HashMap::new(),
-        }
+        RubeGoldberg { chain: KeypadChain::standard(robots) }
     }
 
-
-    fn control_seq(&mut self, code: &str) -> Vec<String> {
-        let mut cmds = self.pad.control_seq(code);
-        println!("cmds: {}", cmds.len());
-        for dpad in self.dpads.iter_mut() {
-            cmds = dpad.control_seq(cmds, &mut self.memo);
-            println!("cmds: {}", cmds.len());
-            // for cmd in cmds.iter() {
-            //     println!("     {}", cmd);
-            // }
-        }
-        cmds
+    /// Fewest presses for the human to type the code, without building any sequences
+    fn presses(&mut self, code: &str) -> usize {
+        self.chain.presses(code)
     }
 
     fn complexity(&mut self, code: &str) -> usize {
         // parse the integer from the string
         let mult = code[0..3].parse::<usize>().unwrap();
-        let seqs = self.control_seq(code);
-
-        let len = seqs.iter().map(|s| s.len()).sum::<usize>();
-        println!("{} * {} = {}    {}", len, mult, mult * len, code);
-        mult * len
+        mult * self.presses(code)
     }
 
     fn solve(&mut self, input: &Game) -> usize {
@@ -336,27 +363,20 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        let mut pad = NumberPad::new();
-        assert_eq!(pad.press_key('A'), "A");
-        assert_eq!(pad.press_key('0'), "<A");
-        assert_eq!(pad.press_key('2'), "^A");
-        assert_eq!(pad.press_key('9'),  "^^>A");
-        assert_eq!(pad.press_key('1'), "<<vvA");
-        assert_eq!(pad.press_key('A'), ">>vA");
-        assert_eq!(pad.control_seq("029A"), vec!["<A", "^A", "^^>A", "vvvA"]);
-
-
-        let mut dpad = DirPad::new();
-        assert_eq!(dpad.press_key('A'), "A");
-        assert_eq!(dpad.press_key('<'), "v<<A");
-        assert_eq!(dpad.press_key('^'), ">^A");
-        assert_eq!(dpad.press_key('v'), "vA");
-        assert_eq!(dpad.press_key('>'), ">A");
-        assert_eq!(dpad.press_key('A'), "^A");
-
-        let mut memo = HashMap::new();
-        assert_eq!(dpad.control_seq(pad.control_seq("029A"), &mut memo).iter().map(|s| s.len()).sum::<usize>(),
-            "v<<A>>^A<A>AvA<^AA>A<vAAA>^A".len());
+        let pad = Keypad::numeric();
+        assert_eq!(pad.moves('A', 'A'), vec!["A"]);
+        assert_eq!(pad.moves('A', '0'), vec!["<A"]);
+        assert_eq!(pad.moves('0', '2'), vec!["^A"]);
+        assert!(pad.moves('2', '9').contains(&"^^>A".to_string()));
+        assert_eq!(pad.moves('9', 'A'), vec!["vvvA"]);
+
+        let dpad = Keypad::directional();
+        assert_eq!(dpad.moves('A', '<'), vec!["<v<A", "v<<A"]);
+        assert_eq!(dpad.moves('<', '^'), vec![">^A"]);
+        assert_eq!(dpad.moves('^', 'v'), vec!["vA"]);
+
+        let mut chain = KeypadChain::new(vec![pad, dpad.clone(), dpad]);
+        assert_eq!(chain.presses("029A"), "v<<A>>^A<A>AvA<^AA>A<vAAA>^A".len());
     }
     // <vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A
     // v<<A>>^A<A>AvA<^AA>A<vAAA>^A
@@ -366,17 +386,14 @@ mod tests {
     #[test]
     fn part1_exampleB() {
         let mut mine = RubeGoldberg::new(2);
-        let seq = mine.control_seq("029A");
-        assert_eq!(seq.iter().map(|s| s.len()).sum::<usize>(), "<vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A".len());
+        assert_eq!(mine.presses("029A"), "<vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A".len());
     }
 
     #[test]
     fn part1_exampleC() {
-        let mut pad = NumberPad::new();
-        println!("{:?}", pad.control_seq("379A"));
         let mut mine = RubeGoldberg::new(2);
         // <v<A>>^AvA^A<vA<AA>>^AAvA<^A>AAvA^A<vA>^AA<A>A<v<A>A>^AAAvA<^A>A
-        assert_eq!(mine.control_seq("379A").len(),
+        assert_eq!(mine.presses("379A"),
                 "<v<A>>^AvA^A<vA<AA>>^AAvA<^A>AAvA^A<vA>^AA<A>A<v<A>A>^AAAvA<^A>A".len());
     }
 
@@ -389,16 +406,123 @@ mod tests {
 
     #[test]
     fn part2_exampleB() {
-        let mut mine = RubeGoldberg::new(18);
-        let seq = mine.control_seq("3");
-        assert_eq!(seq.len(), "<vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A".len());
+        // The cost model's count is met by a sequence that really types the code
+        for robots in 0..4 {
+            let mut chain = KeypadChain::standard(robots);
+            for code in parse(SAMPLE) {
+                let seq = chain.sequence(&code);
+                assert_eq!(seq.len(), chain.presses(&code));
+                assert_eq!(chain.replay(&seq), Ok(code));
+            }
+        }
+        let mut mine = RubeGoldberg::new(0);
+        assert_eq!(mine.presses("029A"), "<A^A>^^AvvvA".len());
     }
 
     #[test]
     fn part2_example() {
         let game = parse(SAMPLE);
         let mut mine = RubeGoldberg::new(25);
-        assert_eq!(mine.solve(&game), 126384);    }
+        assert_eq!(mine.solve(&game), 154115708116294);
+    }
+
+    #[test]
+    fn moves_avoid_gap() {
+        let pad = Keypad::numeric();
+        assert_eq!(pad.gaps(), vec![Point::new(0, 0)]);
+        assert_eq!(pad.moves('A', '1'), vec!["<^<A", "^<<A"]);
+        assert_eq!(pad.moves('7', '0'), vec![">vvvA", "v>vvA", "vv>vA"]);
+        assert_eq!(pad.moves('2', '9').len(), 3);
+        assert_eq!(pad.moves('5', '5'), vec!["A"]);
+        let dpad = Keypad::directional();
+        assert_eq!(dpad.moves('<', 'A'), vec![">>^A", ">^>A"]);
+    }
+
+    #[test]
+    fn replay_sequences() {
+        let chain = KeypadChain::standard(2);
+        assert_eq!(chain.replay("<vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A"), Ok("029A".to_string()));
+        assert_eq!(chain.replay("<v<A>>^AvA^A<vA<AA>>^AAvA<^A>AAvA^A<vA>^AA<A>A<v<A>A>^AAAvA<^A>A"), Ok("379A".to_string()));
+
+        // The cost model produces sequences that type the code
+        let mut chain = KeypadChain::standard(2);
+        for code in parse(SAMPLE) {
+            let seq = chain.sequence(&code);
+            assert_eq!(seq.len(), chain.presses(&code));
+            assert_eq!(chain.replay(&seq), Ok(code));
+        }
+    }
+
+    #[test]
+    fn replay_panics() {
+        // Two steps left from A runs the number pad's arm into its gap
+        let chain = KeypadChain::new(vec![Keypad::numeric(), Keypad::directional()]);
+        assert_eq!(chain.replay("<A<A"), Err(ReplayError::Panic(Panic { step: 2, pad: 0, pos: Point::new(0, 0) })));
+        // Left of the middle robot's ^ key is a gap too
+        let chain = KeypadChain::standard(1);
+        assert_eq!(chain.replay("v<<A"), Ok("".to_string()));
+        assert_eq!(chain.replay("<A<A"), Err(ReplayError::Panic(Panic { step: 2, pad: 1, pos: Point::new(0, 1) })));
+        // Typing directly on one pad can't miss
+        assert_eq!(KeypadChain::new(vec![Keypad::numeric()]).replay("029A"), Ok("029A".to_string()));
+        // Keys the human doesn't have are reported, not pressed
+        assert_eq!(chain.replay("<vX"), Err(ReplayError::UnknownKey { step: 2, key: 'X' }));
+        assert_eq!(KeypadChain::new(vec![Keypad::numeric()]).replay("02<"), Err(ReplayError::UnknownKey { step: 2, key: '<' }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Controlling pads only have directions and A")]
+    fn controllers_only_steer() {
+        KeypadChain::new(vec![Keypad::numeric(), Keypad::parse(" ^A\n<v>\n  X")]);
+    }
+
+    #[test]
+    fn keypad_chains() {
+        let mut chain = KeypadChain::new(vec![Keypad::numeric(), Keypad::directional()]);
+        assert_eq!(chain.presses("029A"), "<A^A>^^AvvvA".len());
+
+        // Typing directly on a hex pad costs one press per key plus the distance between them
+        let hex = Keypad::parse("CDEF\n89AB\n4567\n0123");
+        let mut chain = KeypadChain::new(vec![hex.clone()]);
+        assert_eq!(chain.presses("F00D"), 4);
+        let mut chain = KeypadChain::new(vec![hex.clone(), Keypad::directional()]);
+        assert_eq!(chain.presses("F00D"), 3 + 7 + 1 + 5);
+
+        // A gap between 1 and 2, so the only way across goes around underneath
+        let pad = Keypad::parse("1 2\n3A4");
+        assert_eq!(pad.gaps(), vec![Point::new(1, 1)]);
+        assert_eq!(pad.moves('1', '2'), vec!["v>>^A"]);
+        let mut chain = KeypadChain::new(vec![pad, Keypad::directional()]);
+        assert_eq!(chain.presses("12"), 3 + 5);
+
+        // Two gaps stacked up, so going around takes the bottom row
+        let pad = Keypad::parse("1 2\n3 4\n5A6");
+        assert_eq!(pad.gaps(), vec![Point::new(1, 1), Point::new(1, 2)]);
+        assert_eq!(pad.moves('1', '2'), vec!["vv>>^^A"]);
+        assert_eq!(pad.moves('A', '1'), vec!["<^^A"]);
+        assert_eq!(pad.moves('3', '4'), vec!["v>>^A"]);
+        let mut chain = KeypadChain::new(vec![pad, Keypad::directional()]);
+        assert_eq!(chain.presses("12"), 4 + 7);
+
+        // A differently laid out pad for the middle robot
+        let flat = Keypad::parse("<^v>A");
+        let mut chain = KeypadChain::new(vec![Keypad::numeric(), flat, Keypad::directional()]);
+        let mut standard = KeypadChain::standard(1);
+        assert!(chain.presses("029A") != standard.presses("029A"));
+        assert_eq!(KeypadChain::standard(2).presses("029A"), 68);
+    }
+
+    #[test]
+    fn generated_codes() {
+        let codes = parse(&crate::gen::door_codes(21, 20).input);
+        for robots in 0..3 {
+            let mut chain = KeypadChain::standard(robots);
+            for code in codes.iter() {
+                let seq = chain.sequence(code);
+                assert_eq!(seq.len(), chain.presses(code));
+                assert_eq!(chain.replay(&seq), Ok(code.clone()));
+            }
+        }
+    }
 }
 
 // 80 * 805 = 64400    805A
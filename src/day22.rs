@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 #[aoc_generator(day22)]
@@ -60,6 +60,276 @@ fn print_seqs(input: &Vec<usize>) -> usize {
     0
 }
 
+// The same step as prng, on 24-bit secrets held in u32 so a batch fits in vector registers
+const MASK: u32 = 0xFF_FFFF;
+
+#[inline(always)]
+fn prng32(x: u32) -> u32 {
+    let x = (x ^ (x << 6)) & MASK;
+    let x = x ^ (x >> 5);
+    (x ^ (x << 11)) & MASK
+}
+
+// Secrets are stepped this many at a time, in fixed-size arrays the compiler can vectorize
+const LANES: usize = 16;
+
+/// Step every secret once
+pub fn prng_batch(secrets: &mut [u32]) {
+    let mut chunks = secrets.chunks_exact_mut(LANES);
+    for chunk in chunks.by_ref() {
+        let lanes: &mut [u32; LANES] = chunk.try_into().unwrap();
+        for x in lanes.iter_mut() {
+            *x = prng32(*x);
+        }
+    }
+    for x in chunks.into_remainder() {
+        *x = prng32(*x);
+    }
+}
+
+// Undo y = (x ^ (x << k)) & MASK.  Each pass recovers k more low bits of x.
+fn unshift_left(y: u32, k: u32) -> u32 {
+    let mut x = y;
+    for _ in 0..24 / k {
+        x = (y ^ (x << k)) & MASK;
+    }
+    x
+}
+
+// Undo y = x ^ (x >> k).  Each pass recovers k more high bits of x.
+fn unshift_right(y: u32, k: u32) -> u32 {
+    let mut x = y;
+    for _ in 0..24 / k {
+        x = y ^ (x >> k);
+    }
+    x
+}
+
+/// The secret that comes before x
+pub fn prng_inverse(x: u32) -> u32 {
+    let x = unshift_left(x, 11);
+    let x = unshift_right(x, 5);
+    unshift_left(x, 6)
+}
+
+/// The PRNG is linear over GF(2), so a step is a 24x24 bit matrix.  Column j is where bit j goes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BitMatrix([u32; 24]);
+
+impl BitMatrix {
+    pub fn identity() -> BitMatrix {
+        BitMatrix(std::array::from_fn(|j| 1 << j))
+    }
+
+    /// One step of the PRNG
+    pub fn step() -> BitMatrix {
+        BitMatrix(std::array::from_fn(|j| prng32(1 << j)))
+    }
+
+    pub fn apply(&self, x: u32) -> u32 {
+        (0..24).filter(|j| x & (1 << j) != 0).fold(0, |y, j| y ^ self.0[j])
+    }
+
+    /// self after other
+    pub fn compose(&self, other: &BitMatrix) -> BitMatrix {
+        BitMatrix(std::array::from_fn(|j| self.apply(other.0[j])))
+    }
+
+    pub fn pow(&self, mut n: u64) -> BitMatrix {
+        let mut result = BitMatrix::identity();
+        let mut base = *self;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.compose(&base);
+            }
+            base = base.compose(&base);
+            n >>= 1;
+        }
+        result
+    }
+}
+
+/// The secret n steps after x, without stepping n times
+pub fn jump(x: u32, n: u64) -> u32 {
+    BitMatrix::step().pow(n).apply(x & MASK)
+}
+
+/// Where a seed's sequence of secrets starts repeating
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    /// Steps before we first reach the cycle
+    pub tail: usize,
+    pub length: usize,
+}
+
+/// Find the cycle a seed falls into, with Brent's algorithm.  Since every step can be undone, the tail is always empty.
+pub fn cycle(seed: u32) -> Cycle {
+    let seed = seed & MASK;
+    let (mut power, mut length) = (1, 1);
+    let mut tortoise = seed;
+    let mut hare = prng32(seed);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare;
+            power *= 2;
+            length = 0;
+        }
+        hare = prng32(hare);
+        length += 1;
+    }
+
+    let (mut tortoise, mut hare) = (seed, seed);
+    for _ in 0..length {
+        hare = prng32(hare);
+    }
+    let mut tail = 0;
+    while tortoise != hare {
+        tortoise = prng32(tortoise);
+        hare = prng32(hare);
+        tail += 1;
+    }
+    Cycle { tail, length }
+}
+
+/// How many cycles of each length the PRNG splits all 2^24 secrets into
+pub fn cycle_structure() -> BTreeMap<usize, usize> {
+    let mut seen = vec![false; MASK as usize + 1];
+    let mut structure = BTreeMap::new();
+    for seed in 0..=MASK {
+        if seen[seed as usize] {
+            continue;
+        }
+        let mut x = seed;
+        let mut length = 0;
+        while !seen[x as usize] {
+            seen[x as usize] = true;
+            x = prng32(x);
+            length += 1;
+        }
+        *structure.entry(length).or_insert(0) += 1;
+    }
+    structure
+}
+
+// Four price changes, each in -9..=9, packed into one index in 0..19^4
+const WINDOWS: usize = 19 * 19 * 19 * 19;
+
+fn push_change(window: usize, change: i32) -> usize {
+    (window * 19 + (change + 9) as usize) % WINDOWS
+}
+
+fn decode(window: usize) -> [i8; 4] {
+    let mut seq = [0i8; 4];
+    let mut w = window;
+    for change in seq.iter_mut().rev() {
+        *change = (w % 19) as i8 - 9;
+        w /= 19;
+    }
+    seq
+}
+
+fn encode(seq: &[i8; 4]) -> usize {
+    seq.iter().fold(0, |w, c| push_change(w, *c as i32))
+}
+
+/// What happens when we tell the monkey to sell on a sequence of price changes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sale {
+    pub sequence: [i8; 4],
+    pub bananas: u32,
+
+    /// What each buyer sold for, if the sequence came up at all
+    pub prices: Vec<Option<u8>>,
+}
+
+/// All the buyers at the market and the prices they offer over the day
+pub struct Market {
+    seeds: Vec<u32>,
+    rounds: usize,
+
+    // prices[r * buyers + b] is what buyer b offers after r new secrets
+    prices: Vec<u8>,
+}
+
+impl Market {
+    pub fn new(seeds: &[usize], rounds: usize) -> Market {
+        let seeds: Vec<u32> = seeds.iter().map(|s| *s as u32 & MASK).collect();
+        let mut secrets = seeds.clone();
+        let mut prices = Vec::with_capacity(seeds.len() * (rounds + 1));
+        prices.extend(secrets.iter().map(|x| (x % 10) as u8));
+        for _ in 0..rounds {
+            prng_batch(&mut secrets);
+            prices.extend(secrets.iter().map(|x| (x % 10) as u8));
+        }
+        Market { seeds, rounds, prices }
+    }
+
+    pub fn buyers(&self) -> usize {
+        self.seeds.len()
+    }
+
+    /// Every buyer's secret at the end of the day
+    pub fn secrets(&self) -> Vec<u32> {
+        let mut secrets = self.seeds.clone();
+        for _ in 0..self.rounds {
+            prng_batch(&mut secrets);
+        }
+        secrets
+    }
+
+    fn price(&self, buyer: usize, round: usize) -> u8 {
+        self.prices[round * self.buyers() + buyer]
+    }
+
+    // Call f with each window of changes the buyer sees, and the price at the end of it
+    fn windows(&self, buyer: usize, mut f: impl FnMut(usize, u8)) {
+        let mut window = 0;
+        for round in 1..=self.rounds {
+            let price = self.price(buyer, round);
+            window = push_change(window, price as i32 - self.price(buyer, round - 1) as i32);
+            if round >= 4 {
+                f(window, price);
+            }
+        }
+    }
+
+    /// What each buyer pays for a sequence: the first time it comes up, if ever
+    pub fn sale(&self, sequence: [i8; 4]) -> Sale {
+        let target = encode(&sequence);
+        let prices: Vec<Option<u8>> = (0..self.buyers()).map(|buyer| {
+            let mut sold = None;
+            self.windows(buyer, |window, price| {
+                if window == target && sold.is_none() {
+                    sold = Some(price);
+                }
+            });
+            sold
+        }).collect();
+        let bananas = prices.iter().flatten().map(|p| *p as u32).sum();
+        Sale { sequence, bananas, prices }
+    }
+
+    /// The sequence that gets us the most bananas across all buyers
+    pub fn best_sale(&self) -> Sale {
+        let mut totals = vec![0u32; WINDOWS];
+
+        // Which buyer last saw each window, plus one, so we only count the first sale without clearing between buyers
+        let mut seen = vec![0u32; WINDOWS];
+        for buyer in 0..self.buyers() {
+            let stamp = buyer as u32 + 1;
+            self.windows(buyer, |window, price| {
+                if seen[window] != stamp {
+                    seen[window] = stamp;
+                    totals[window] += price as u32;
+                }
+            });
+        }
+        // Ties go to the lowest window, so the answer doesn't depend on iteration order
+        let best = (0..WINDOWS).max_by_key(|w| (totals[*w], std::cmp::Reverse(*w))).unwrap();
+        self.sale(decode(best))
+    }
+}
+
 #[aoc(day22, part1)]
 fn part1(input: &Vec<usize>) -> usize {
     let mut monkeys = input.clone();
@@ -69,6 +339,11 @@ fn part1(input: &Vec<usize>) -> usize {
     monkeys.iter().sum()
 }
 
+#[aoc(day22, part1, market)]
+fn part1_market(input: &[usize]) -> usize {
+    Market::new(input, 2000).secrets().iter().map(|x| *x as usize).sum()
+}
+
 #[aoc(day22, part2)]
 fn part2(input: &Vec<usize>) -> usize {
     let mut map = HashMap::new();
@@ -81,6 +356,11 @@ fn part2(input: &Vec<usize>) -> usize {
     *map.values().max().unwrap() as usize
 }
 
+#[aoc(day22, part2, market)]
+fn part2_market(input: &[usize]) -> usize {
+    Market::new(input, 2000).best_sale().bananas as usize
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -133,4 +413,86 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(&SAMPLE2.to_vec()), 23);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn batch_matches_prng() {
+        let seeds: Vec<usize> = (0..37).map(|i| i * 7919 + 123).collect();
+        let mut batch: Vec<u32> = seeds.iter().map(|s| *s as u32).collect();
+        for _ in 0..10 {
+            prng_batch(&mut batch);
+        }
+        for (seed, x) in seeds.iter().zip(batch) {
+            assert_eq!((0..10).fold(*seed, |x, _| prng(x)), x as usize);
+        }
+    }
+
+    #[test]
+    fn windows_round_trip() {
+        for seq in [[-2, 1, -1, 3], [9, -9, 0, 0], [-9, -9, -9, -9]] {
+            assert_eq!(decode(encode(&seq)), seq);
+        }
+    }
+
+    #[test]
+    fn market_example() {
+        assert_eq!(part1_market(SAMPLE), 37327623);
+        let market = Market::new(SAMPLE2, 2000);
+        let sale = market.best_sale();
+        assert_eq!(sale.sequence, [-2, 1, -1, 3]);
+        assert_eq!(sale.bananas, 23);
+        assert_eq!(sale.prices, vec![Some(7), Some(7), None, Some(9)]);
+        assert_eq!(part2_market(SAMPLE2), part2(&SAMPLE2.to_vec()));
+
+        // The puzzle's walk through 123's first ten prices
+        let market = Market::new(&[123], 10);
+        assert_eq!(market.sale([-1, -1, 0, 2]).prices, vec![Some(6)]);
+    }
+
+    // Deterministic spread of seeds over the whole 24-bit space
+    fn seeds() -> impl Iterator<Item = u32> {
+        (0..500u32).map(|i| i.wrapping_mul(2654435761) & MASK).chain([0, 1, MASK])
+    }
+
+    #[test]
+    fn inverse_undoes_prng() {
+        for x in seeds() {
+            assert_eq!(prng_inverse(prng32(x)), x);
+            assert_eq!(prng32(prng_inverse(x)), x);
+        }
+        assert_eq!(prng_inverse(15887950), 123);
+    }
+
+    #[test]
+    fn jump_matches_stepping() {
+        for (i, x) in seeds().enumerate() {
+            let n = (i * 37) % 2001;
+            assert_eq!(jump(x, n as u64) as usize, (0..n).fold(x as usize, |x, _| prng(x)));
+        }
+        assert_eq!(jump(1, 2000), 8685429);
+        assert_eq!(jump(2024, 2000), 8667524);
+        assert_eq!(BitMatrix::step().pow(0), BitMatrix::identity());
+    }
+
+    #[test]
+    fn cycles() {
+        // Zero maps to itself; everything else lives on cycles with no tail
+        assert_eq!(cycle(0), Cycle { tail: 0, length: 1 });
+        for x in seeds().take(3).filter(|x| *x != 0) {
+            let c = cycle(x);
+            assert_eq!(c.tail, 0);
+            assert_eq!(jump(x, c.length as u64), x);
+        }
+        let structure = cycle_structure();
+        assert_eq!(structure.iter().map(|(len, n)| len * n).sum::<usize>(), 1 << 24);
+        assert_eq!(structure.get(&1), Some(&1));
+        assert!(structure.contains_key(&cycle(123).length));
+    }
+
+    #[test]
+    fn generated_secrets() {
+        let secrets = crate::gen::secrets(22, 20);
+        let input = parse(&secrets.input);
+        assert_eq!(part1(&input), secrets.answer);
+        assert_eq!(part1_market(&input), secrets.answer);
+    }
+}
@@ -3,6 +3,8 @@ use std::collections::{HashMap, HashSet};
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::any;
 
+use crate::graph::Graph;
+
 type Map = HashMap<String, HashSet<String>>;
 #[aoc_generator(day23)]
 fn parse(input: &str) -> Map {
@@ -34,6 +36,26 @@ fn grow_party(map: &Map, nodes: Vec<String>, common: &HashSet<String>) -> Vec<Ve
 
 #[aoc(day23, part1)]
 fn part1(input: &Map) -> usize {
+    let graph = Graph::from_map(input);
+    graph.triangles().iter()
+        .filter(|t| t.iter().any(|id| graph.name(*id).starts_with('t')))
+        .count()
+}
+
+#[aoc(day23, part2)]
+fn part2(input: &Map) -> String {
+    let graph = Graph::from_map(input);
+    graph.names(&graph.maximum_clique().unwrap()).join(",")
+}
+
+/// Graphviz source for the network, with the LAN party lit up
+pub fn to_dot(input: &Map) -> String {
+    let graph = Graph::from_map(input);
+    graph.to_dot(&graph.maximum_clique().unwrap_or_default())
+}
+
+#[aoc(day23, part1, grow_party)]
+fn part1_grow_party(input: &Map) -> usize {
     let parties = grow_party(input, Vec::new(),
             &input.keys().cloned().collect::<HashSet<_>>());
 
@@ -45,8 +67,8 @@ fn part1(input: &Map) -> usize {
 
 }
 
-#[aoc(day23, part2)]
-fn part2(input: &Map) -> String {
+#[aoc(day23, part2, grow_party)]
+fn part2_grow_party(input: &Map) -> String {
     let parties = grow_party(input, Vec::new(),
             &input.keys().cloned().collect::<HashSet<_>>());
 
@@ -64,6 +86,7 @@ fn part2(input: &Map) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::BTreeMap;
 
     const SAMPLE: &str = "kh-tc
 qp-kh
@@ -107,4 +130,44 @@ td-yn";
     fn part2_example() {
         assert_eq!(part2(&parse(SAMPLE)), "co,de,ka,ta");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn grow_party_example() {
+        assert_eq!(part1_grow_party(&parse(SAMPLE)), 7);
+        assert_eq!(part2_grow_party(&parse(SAMPLE)), "co,de,ka,ta");
+    }
+
+    #[test]
+    fn graph_example() {
+        let graph = Graph::from_map(&parse(SAMPLE));
+        assert_eq!(graph.triangles().len(), 12);
+        let largest = graph.maximum_clique_where(|n| n == "yn").unwrap();
+        // Ties with td,wh,yn
+        assert_eq!(graph.names(&largest), vec!["aq", "cg", "yn"]);
+    }
+
+    #[test]
+    fn analytics_example() {
+        let graph = Graph::from_map(&parse(SAMPLE));
+        assert_eq!(graph.components().len(), 1);
+        assert_eq!(graph.degree_distribution(), BTreeMap::from([(4, 16)]));
+        assert!(graph.core_numbers().iter().all(|k| *k == 4));
+        assert!(graph.bridges().is_empty());
+        assert!(graph.articulation_points().is_empty());
+
+        let dot = to_dot(&parse(SAMPLE));
+        assert_eq!(dot.matches("fillcolor=red").count(), 4);
+        assert_eq!(dot.matches("color=red, penwidth").count(), 6);
+        assert!(dot.contains("\"co\" [style=filled, fillcolor=red];"));
+    }
+
+    #[test]
+    fn generated_networks() {
+        for seed in 0..10 {
+            let lan = crate::gen::lan(seed, 60, 3 + seed as usize % 5, 80);
+            let map = parse(&lan.input);
+            assert_eq!(part1(&map), lan.answer.0);
+            assert_eq!(part2(&map), lan.answer.1);
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use itertools::Itertools;
-use crate::grid::Grid;
+use crate::{grid::Grid, line::Line, point::Point};
 
 
 #[aoc_generator(day8)]
@@ -29,13 +29,74 @@ fn solve(game: &Grid, start:i32, end: i32) -> usize {
     antinodes.len()
 }
 
+/// Which points around a pair of antennas count as antinodes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Resonance {
+    /// One on each side, twice as far from one antenna as from the other
+    Pair,
+    /// Every grid point on the line through both antennas
+    Harmonics,
+}
+
+/// Antinodes of one frequency
+pub fn antinodes(game: &Grid, frequency: char, resonance: Resonance) -> HashSet<Point> {
+    let mut antinodes = HashSet::new();
+    let Some(antennas) = game.map.get(&frequency) else { return antinodes };
+    for p in antennas.iter().combinations(2) {
+        match resonance {
+            Resonance::Pair => {
+                let delta = *p[0] - *p[1];
+                antinodes.extend([*p[0] + delta, *p[1] - delta].into_iter().filter(|p| game.valid(p)));
+            }
+            Resonance::Harmonics => {
+                antinodes.extend(Line::new(*p[0], *p[1]).lattice_points(|p| game.valid(p)));
+            }
+        }
+    }
+    antinodes
+}
+
+/// Antinodes of every frequency on the map
+pub fn antinodes_by_frequency(game: &Grid, resonance: Resonance) -> BTreeMap<char, HashSet<Point>> {
+    game.map.keys()
+        .filter(|c| **c != '.')
+        .map(|c| (*c, antinodes(game, *c, resonance)))
+        .collect()
+}
+
+/// Antinodes of any frequency
+pub fn all_antinodes(game: &Grid, resonance: Resonance) -> HashSet<Point> {
+    antinodes_by_frequency(game, resonance).into_values().flatten().collect()
+}
+
+/// The map with antinodes marked `#`, except where an antenna is already standing
+pub fn render(game: &Grid, antinodes: &HashSet<Point>) -> String {
+    let mut overlay = game.clone();
+    for p in antinodes.iter() {
+        if overlay[p] == '.' {
+            overlay.set(p, '#');
+        }
+    }
+    overlay.to_string()
+}
+
 #[aoc(day8, part1)]
 fn part1(game: &Grid) -> usize {
-    solve(game, 1, 2)
+    all_antinodes(game, Resonance::Pair).len()
 }
 
 #[aoc(day8, part2)]
 fn part2(game: &Grid) -> usize {
+    all_antinodes(game, Resonance::Harmonics).len()
+}
+
+#[aoc(day8, part1, raw_delta)]
+fn part1_raw_delta(game: &Grid) -> usize {
+    solve(game, 1, 2)
+}
+
+#[aoc(day8, part2, raw_delta)]
+fn part2_raw_delta(game: &Grid) -> usize {
     solve(game, 0, game.width.max(game.height))
 }
 
@@ -62,4 +123,60 @@ mod tests {
         assert_eq!(part1(&input_generator(SAMPLE)), 14);
         assert_eq!(part2(&input_generator(SAMPLE)), 34);
     }
+
+    #[test]
+    fn raw_delta() {
+        assert_eq!(part1_raw_delta(&input_generator(SAMPLE)), 14);
+        assert_eq!(part2_raw_delta(&input_generator(SAMPLE)), 34);
+    }
+
+    #[test]
+    fn frequencies() {
+        let game = input_generator(SAMPLE);
+        let by_frequency = antinodes_by_frequency(&game, Resonance::Pair);
+        assert_eq!(by_frequency.keys().collect::<Vec<_>>(), vec![&'0', &'A']);
+        assert_eq!(by_frequency[&'A'].len(), 5);
+        assert_eq!(render(&game, &all_antinodes(&game, Resonance::Pair)), "......#....#
+...#....0...
+....#0....#.
+..#....0....
+....0....#..
+.#....A.....
+...#........
+#......#....
+........A...
+.........A..
+..........#.
+..........#.");
+    }
+
+    #[test]
+    fn unreduced_steps() {
+        // The antennas are (2,4) apart, so (1,2) is in between them
+        let game = input_generator("..a..
+.....
+.....
+.....
+a....");
+        let harmonics = all_antinodes(&game, Resonance::Harmonics);
+        assert_eq!(harmonics, HashSet::from([Point::new(0, 0), Point::new(1, 2), Point::new(2, 4)]));
+        assert_eq!(part2_raw_delta(&game), 2);
+        assert_eq!(render(&game, &harmonics), "..a..
+.....
+.#...
+.....
+a....");
+    }
+
+    #[test]
+    fn generated_maps() {
+        for seed in 0..10 {
+            let game = input_generator(&crate::gen::antennas(seed, 30, 20, 8).input);
+            assert_eq!(part1(&game), part1_raw_delta(&game));
+            // Stepping by the raw delta skips harmonics in between, so it can only find fewer
+            let harmonics = all_antinodes(&game, Resonance::Harmonics);
+            assert!(all_antinodes(&game, Resonance::Pair).is_subset(&harmonics));
+            assert!(harmonics.len() >= part2_raw_delta(&game));
+        }
+    }
 }
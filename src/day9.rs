@@ -1,14 +1,14 @@
-use std::collections::HashMap;
+use std::{cmp::Reverse, collections::{BTreeMap, BinaryHeap, HashMap}};
 use itertools::Itertools;
 
 #[derive(Debug,Clone)]
-struct File {
-    id: i32,
-    len: i32,
-    gap: i32,
+pub struct File {
+    pub id: i32,
+    pub len: i32,
+    pub gap: i32,
 }
 
-type Disk = Vec<File>;
+pub type Disk = Vec<File>;
 
 #[aoc_generator(day9)]
 fn input_generator(input: &str) -> Disk {
@@ -85,22 +85,21 @@ fn solve(disk: &Disk) -> usize {
     let mut total = 0usize;
     let mut count = 0usize;
     let mut skid = Skid::new(disk);
-    let mut decr = 0i32;
 
     for d in disk.iter() {
         let (id, done) = skid.peek();
-        if id == d.id {
-            decr = done;
+        if id < d.id {
+            // This file was already moved into earlier gaps
+            return total;
         }
+        // Skip the blocks that the skid already moved
+        let decr = if id == d.id { done } else { 0 };
         for _ in 0..d.len-decr {
             total += count * d.id as usize;
             count += 1;
         }
         for _ in 0..d.gap {
-            let (id, ddd) = skid.next();
-            if id == d.id + 1 {
-                decr = ddd;
-            }
+            let (id, _) = skid.next();
             if id == d.id {
                 return total;
             }
@@ -108,11 +107,13 @@ fn solve(disk: &Disk) -> usize {
             count += 1;
         }
     }
-    0 // unreachable!();
+    // Only reached when there were no gaps to fill
+    total
 }
 
 
-fn checksum(disk: &Disk) -> usize {
+/// Checksum of any disk layout, compacted or not
+pub fn checksum(disk: &Disk) -> usize {
     let mut total = 0usize;
     let mut count = 0usize;
 
@@ -125,6 +126,32 @@ fn checksum(disk: &Disk) -> usize {
     }
     total
 }
+/// Write a disk back in the dense puzzle format.  Ids aren't stored in that format, only the order of
+/// the files, so files are numbered from 0 again on the way back in.  Runs longer than 9 are split with
+/// an empty gap (or empty file) between the pieces, and the pieces get numbers of their own.
+pub fn to_dense(disk: &Disk) -> String {
+    let mut digits: Vec<u32> = Vec::new();
+    for d in disk.iter() {
+        for (is_file, len) in [(true, d.len as u32), (false, d.gap as u32)] {
+            if !is_file && len == 0 {
+                continue;
+            }
+            let mut left = len;
+            loop {
+                // Even slots are files, odd ones gaps
+                if digits.len().is_multiple_of(2) != is_file {
+                    digits.push(0);
+                }
+                digits.push(left.min(9));
+                left -= left.min(9);
+                if left == 0 {
+                    break;
+                }
+            }
+        }
+    }
+    digits.iter().map(|d| char::from_digit(*d, 10).unwrap()).collect()
+}
 
 fn solve2(disk: &Disk) -> usize {
     let mut disk: Disk = disk.clone();
@@ -206,13 +233,265 @@ fn solve2(disk: &Disk) -> usize {
     checksum(&disk)
 }
 
+/// How to close up the free space on a disk
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Compaction {
+    /// Move single blocks from the end of the disk into the leftmost free block
+    Blocks,
+    /// Move whole files, highest id first, into the leftmost gap that fits
+    FirstFit,
+    /// Move whole files, highest id first, into the smallest gap that fits (leftmost on ties)
+    BestFit,
+    /// Slide every file left over the gaps, keeping the files in order
+    DefragmentLeft,
+}
+
+/// The disk one block at a time: each block holds a file id or is free
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiskMap {
+    blocks: Vec<Option<u32>>,
+}
+
+impl DiskMap {
+    pub fn new(disk: &Disk) -> DiskMap {
+        let mut blocks = Vec::new();
+        for d in disk.iter() {
+            blocks.extend(std::iter::repeat_n(Some(d.id as u32), d.len as usize));
+            blocks.extend(std::iter::repeat_n(None, d.gap as usize));
+        }
+        DiskMap { blocks }
+    }
+
+    pub fn parse_dense(input: &str) -> DiskMap {
+        DiskMap::new(&input_generator(input))
+    }
+
+    /// Read a comma separated list of blocks, with `.` for free ones
+    pub fn parse_block_list(input: &str) -> Option<DiskMap> {
+        let blocks = input.trim()
+            .split(',')
+            .map(|b| match b.trim() {
+                "." => Some(None),
+                id => id.parse().ok().map(Some),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(DiskMap { blocks })
+    }
+
+    /// Every block, comma separated, with `.` for free ones.  Unlike the dense format this keeps the ids.
+    pub fn to_block_list(&self) -> String {
+        self.blocks.iter()
+            .map(|id| id.map_or(".".to_string(), |id| id.to_string()))
+            .join(",")
+    }
+
+    /// The layout as a list of files, each followed by its gap.  Free space at the very start
+    /// becomes an empty file 0.
+    pub fn to_disk(&self) -> Disk {
+        let mut disk = Vec::new();
+        for (id, _, len) in self.runs() {
+            match (id, disk.last_mut()) {
+                (Some(id), _) => disk.push(File { id: id as i32, len: len as i32, gap: 0 }),
+                (None, Some(last)) => last.gap = len as i32,
+                (None, None) => disk.push(File { id: 0, len: 0, gap: len as i32 }),
+            }
+        }
+        disk
+    }
+
+    pub fn to_dense(&self) -> String {
+        to_dense(&self.to_disk())
+    }
+
+    pub fn blocks(&self) -> &[Option<u32>] {
+        &self.blocks
+    }
+
+    /// Runs of blocks holding the same file, as (id, start, len) in disk order
+    pub fn files(&self) -> Vec<(u32, usize, usize)> {
+        self.runs().into_iter().filter_map(|(id, start, len)| id.map(|id| (id, start, len))).collect()
+    }
+
+    /// Runs of free blocks, as (start, len) in disk order
+    pub fn gaps(&self) -> Vec<(usize, usize)> {
+        self.runs().into_iter().filter(|(id, _, _)| id.is_none()).map(|(_, start, len)| (start, len)).collect()
+    }
+
+    fn runs(&self) -> Vec<(Option<u32>, usize, usize)> {
+        self.blocks.iter()
+            .enumerate()
+            .chunk_by(|(_, id)| **id)
+            .into_iter()
+            .map(|(id, run)| {
+                let run: Vec<_> = run.collect();
+                (id, run[0].0, run.len())
+            })
+            .collect()
+    }
+
+    pub fn checksum(&self) -> usize {
+        self.blocks.iter()
+            .enumerate()
+            .filter_map(|(pos, id)| id.map(|id| pos * id as usize))
+            .sum()
+    }
+
+    pub fn compacted(&self, strategy: Compaction) -> DiskMap {
+        let mut disk = self.clone();
+        disk.compact(strategy);
+        disk
+    }
+
+    pub fn compact(&mut self, strategy: Compaction) {
+        match strategy {
+            Compaction::Blocks => self.compact_blocks(),
+            Compaction::FirstFit => self.compact_files(false),
+            Compaction::BestFit => self.compact_files(true),
+            Compaction::DefragmentLeft => {
+                let len = self.blocks.len();
+                self.blocks.retain(|id| id.is_some());
+                self.blocks.resize(len, None);
+            }
+        }
+    }
+
+    fn compact_blocks(&mut self) {
+        let (mut free, mut used) = (0, self.blocks.len());
+        loop {
+            while free < self.blocks.len() && self.blocks[free].is_some() {
+                free += 1;
+            }
+            while used > 0 && self.blocks[used - 1].is_none() {
+                used -= 1;
+            }
+            if used == 0 || free >= used - 1 {
+                break;
+            }
+            self.blocks.swap(free, used - 1);
+        }
+    }
+
+    // A min-heap of free run starts per run length, so the leftmost run of any size is cheap to find.
+    // A moved file's old blocks are merged with the free runs either side and put back, since files
+    // further along may fit there if the disk isn't laid out in id order.
+    fn compact_files(&mut self, best_fit: bool) {
+        let mut free = FreeRuns::new(self.blocks.len());
+        for (start, len) in self.gaps() {
+            free.insert(start, len);
+        }
+
+        let mut files = self.files();
+        files.sort_by_key(|(id, start, _)| (Reverse(*id), Reverse(*start)));
+        for (id, start, len) in files {
+            // (gap start, gap len) candidates to the left of the file
+            let candidates = free.leftmost(len).into_iter()
+                .filter(|(at, _)| *at < start);
+            let gap = if best_fit {
+                candidates.min_by_key(|(at, size)| (*size, *at))
+            } else {
+                candidates.min()
+            };
+            let Some((at, size)) = gap else { continue };
+            free.remove(at, size);
+            if size > len {
+                free.insert(at + len, size - len);
+            }
+            self.blocks[at..at + len].fill(Some(id));
+            self.blocks[start..start + len].fill(None);
+
+            // Join the freed blocks up with any free run on either side
+            let mut first = start;
+            while first > 0 && self.blocks[first - 1].is_none() {
+                first -= 1;
+            }
+            let mut end = start + len;
+            while end < self.blocks.len() && self.blocks[end].is_none() {
+                end += 1;
+            }
+            if first < start {
+                free.remove(first, start - first);
+            }
+            if end > start + len {
+                free.remove(start + len, end - start - len);
+            }
+            free.insert(first, end - first);
+        }
+    }
+}
+
+// Starts of free runs, in a min-heap per run length.  Runs that have been used or merged aren't dug
+// out of their heaps; they're dropped when they reach the top and no longer match `len_at`.
+struct FreeRuns {
+    heaps: BTreeMap<usize, BinaryHeap<Reverse<usize>>>,
+    // Length of the free run starting at each block, or 0
+    len_at: Vec<usize>,
+}
+
+impl FreeRuns {
+    fn new(blocks: usize) -> FreeRuns {
+        FreeRuns { heaps: BTreeMap::new(), len_at: vec![0; blocks] }
+    }
+
+    fn insert(&mut self, start: usize, len: usize) {
+        self.len_at[start] = len;
+        self.heaps.entry(len).or_default().push(Reverse(start));
+    }
+
+    fn remove(&mut self, start: usize, len: usize) {
+        if self.len_at[start] == len {
+            self.len_at[start] = 0;
+        }
+    }
+
+    // The leftmost run of each length from `min` up, as (start, len)
+    fn leftmost(&mut self, min: usize) -> Vec<(usize, usize)> {
+        let mut runs = Vec::new();
+        let mut emptied = Vec::new();
+        for (len, heap) in self.heaps.range_mut(min..) {
+            while heap.peek().is_some_and(|Reverse(start)| self.len_at[*start] != *len) {
+                heap.pop();
+            }
+            match heap.peek() {
+                Some(Reverse(start)) => runs.push((*start, *len)),
+                None => emptied.push(*len),
+            }
+        }
+        for len in emptied {
+            self.heaps.remove(&len);
+        }
+        runs
+    }
+}
+
+impl core::fmt::Display for DiskMap {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for id in self.blocks.iter() {
+            match id {
+                Some(id) => write!(f, "{}", id % 10)?,
+                None => write!(f, ".")?,
+            }
+        }
+        Ok(())
+    }
+}
+
 #[aoc(day9, part1)]
 fn part1(disk: &Disk) -> usize {
-    solve(disk)
+    DiskMap::new(disk).compacted(Compaction::Blocks).checksum()
 }
 
 #[aoc(day9, part2)]
 fn part2(disk: &Disk) -> usize {
+    DiskMap::new(disk).compacted(Compaction::FirstFit).checksum()
+}
+
+#[aoc(day9, part1, skid)]
+fn part1_skid(disk: &Disk) -> usize {
+    solve(disk)
+}
+
+#[aoc(day9, part2, gap_ids)]
+fn part2_gap_ids(disk: &Disk) -> usize {
     solve2(disk)
 }
 
@@ -220,6 +499,7 @@ fn part2(disk: &Disk) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::harness::{shrink_string, Harness, Rng};
 
     const SAMPLE: &str = "2333133121414131402";
     //    00...111...2...333.44.5555.6666.777.888899
@@ -238,4 +518,136 @@ mod tests {
         assert_eq!(part2(&input_generator(SAMPLE)), 2858);
         // assert_eq!(part2(&input_generator(SAMPLE)), 1928);
     }
+
+    #[test]
+    fn render() {
+        let disk = DiskMap::new(&input_generator(SAMPLE));
+        assert_eq!(disk.to_string(), "00...111...2...333.44.5555.6666.777.888899");
+        assert_eq!(DiskMap::new(&input_generator("12345")).to_string(), "0..111....22222");
+    }
+
+    #[test]
+    fn strategies() {
+        let disk = DiskMap::new(&input_generator(SAMPLE));
+        let blocks = disk.compacted(Compaction::Blocks);
+        assert_eq!(blocks.to_string(), "0099811188827773336446555566..............");
+        assert_eq!(blocks.checksum(), 1928);
+        assert_eq!(part1_skid(&input_generator(SAMPLE)), 1928);
+
+        let first = disk.compacted(Compaction::FirstFit);
+        assert_eq!(first.to_string(), "00992111777.44.333....5555.6666.....8888..");
+        assert_eq!(first.checksum(), 2858);
+        assert_eq!(part2_gap_ids(&input_generator(SAMPLE)), 2858);
+
+        let best = disk.compacted(Compaction::BestFit);
+        assert_eq!(best.to_string(), "00992111777.44.333....5555.6666.....8888..");
+
+        let left = disk.compacted(Compaction::DefragmentLeft);
+        assert_eq!(left.to_string(), format!("{}{}", "0011123334455556666777888899", ".".repeat(14)));
+    }
+
+    #[test]
+    fn best_fit_prefers_small_gaps() {
+        // 0....1..22
+        let disk = DiskMap::new(&input_generator("14122"));
+        assert_eq!(disk.to_string(), "0....1..22");
+        assert_eq!(disk.compacted(Compaction::FirstFit).to_string(), "0221......");
+        assert_eq!(disk.compacted(Compaction::BestFit).to_string(), "01....22..");
+
+        // Space a file leaves behind is used again when the ids aren't in disk order
+        let disk = DiskMap::parse_block_list(".,.,1,1,0,0").unwrap();
+        assert_eq!(disk.compacted(Compaction::FirstFit).to_string(), "1100..");
+        assert_eq!(disk.compacted(Compaction::BestFit).to_string(), "1100..");
+
+        // Shuffled layouts, and files split up by block compaction, against scanning for each gap
+        let mut rng = Rng::new(41);
+        for _ in 0..200 {
+            let mut blocks = DiskMap::parse_dense(&random_disk(&mut rng)).blocks().to_vec();
+            rng.shuffle(&mut blocks);
+            let mut disk = DiskMap { blocks };
+            if rng.one_in(2) {
+                disk.compact(Compaction::Blocks);
+                rng.shuffle(&mut disk.blocks);
+            }
+            assert_eq!(disk.compacted(Compaction::FirstFit), first_fit_scan(&disk), "{}", disk);
+        }
+    }
+
+    fn first_fit_scan(disk: &DiskMap) -> DiskMap {
+        let mut blocks = disk.blocks().to_vec();
+        let mut files = disk.files();
+        files.sort_by_key(|(id, start, _)| (Reverse(*id), Reverse(*start)));
+        for (id, start, len) in files {
+            if let Some(at) = (0..start).find(|at| blocks[*at..*at + len].iter().all(|b| b.is_none())) {
+                blocks[at..at + len].fill(Some(id));
+                blocks[start..start + len].fill(None);
+            }
+        }
+        DiskMap { blocks }
+    }
+
+    #[test]
+    fn round_trip() {
+        let disk = input_generator(SAMPLE);
+        assert_eq!(to_dense(&disk), SAMPLE);
+        let map = DiskMap::parse_dense(SAMPLE);
+        assert_eq!(map.to_dense(), SAMPLE);
+        assert_eq!(map.to_disk().len(), disk.len());
+
+        for strategy in [Compaction::Blocks, Compaction::FirstFit, Compaction::BestFit, Compaction::DefragmentLeft] {
+            let compacted = map.compacted(strategy);
+            assert_eq!(checksum(&compacted.to_disk()), compacted.checksum());
+            assert_eq!(DiskMap::parse_block_list(&compacted.to_block_list()), Some(compacted.clone()));
+
+            // The dense format keeps the layout but not the ids
+            let back = DiskMap::parse_dense(&compacted.to_dense());
+            assert_eq!(back.gaps(), compacted.gaps());
+        }
+        assert_eq!(map.compacted(Compaction::FirstFit).to_dense(), "20201030312134414542");
+    }
+
+    #[test]
+    fn long_runs() {
+        let map = DiskMap::parse_block_list("., ., 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, ., ., ., ., ., ., ., ., ., ., 1").unwrap();
+        assert_eq!(map.to_string(), "..00000000000..........1");
+        assert_eq!(map.to_dense(), "029029011");
+        assert_eq!(DiskMap::parse_dense("029029011").to_string(), "..11111111122..........4");
+        assert_eq!(map.to_block_list(), ".,.,0,0,0,0,0,0,0,0,0,0,0,.,.,.,.,.,.,.,.,.,.,1");
+        assert_eq!(DiskMap::parse_block_list("0,x"), None);
+    }
+
+    fn random_disk(rng: &mut Rng) -> String {
+        crate::gen::disk_map(rng.next_u64(), rng.between(1, 30)).input
+    }
+
+    fn shrink_disk(disk: &str) -> Vec<String> {
+        let mut smaller = shrink_string(disk);
+        // Smaller digits, too
+        for (i, c) in disk.char_indices() {
+            if c > '0' {
+                let mut s = disk.to_string();
+                s.replace_range(i..=i, &((c as u8 - 1) as char).to_string());
+                smaller.push(s);
+            }
+        }
+        // Files can't be empty
+        smaller.retain(|s| !s.is_empty() && s.chars().step_by(2).all(|c| c != '0'));
+        smaller
+    }
+
+    #[test]
+    fn implementations_agree() {
+        let blocks = Harness::new()
+            .register("skid", |s: &String| solve(&input_generator(s)))
+            .register("disk_map", |s: &String| part1(&input_generator(s)));
+        blocks.check(9, 300, random_disk, |s| shrink_disk(s)).unwrap();
+        // Inputs the harness caught the skid getting wrong
+        assert_eq!(solve(&input_generator("101")), 1);
+        assert_eq!(solve(&input_generator("101661")), 55);
+
+        let files = Harness::new()
+            .register("gap_ids", |s: &String| solve2(&input_generator(s)))
+            .register("first_fit", |s: &String| part2(&input_generator(s)));
+        files.check(9, 300, random_disk, |s| shrink_disk(s)).unwrap();
+    }
 }
@@ -0,0 +1,169 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// Union-find over any hashable keys, with path compression and union by rank.
+/// Keys are added on first use.
+#[derive(Clone, Debug, Default)]
+pub struct DisjointSet<K: Eq + Hash + Clone> {
+    index: HashMap<K, usize>,
+    keys: Vec<K>,
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+
+    // Number of keys in each set, valid only at the roots
+    size: Vec<usize>,
+    sets: usize,
+}
+
+impl<K: Eq + Hash + Clone> DisjointSet<K> {
+    pub fn new() -> DisjointSet<K> {
+        DisjointSet {
+            index: HashMap::new(),
+            keys: Vec::new(),
+            parent: Vec::new(),
+            rank: Vec::new(),
+            size: Vec::new(),
+            sets: 0,
+        }
+    }
+
+    /// Add a key in a set of its own, if we don't already have it
+    pub fn insert(&mut self, k: &K) -> usize {
+        if let Some(i) = self.index.get(k) {
+            return *i;
+        }
+        let i = self.keys.len();
+        self.index.insert(k.clone(), i);
+        self.keys.push(k.clone());
+        self.parent.push(i);
+        self.rank.push(0);
+        self.size.push(1);
+        self.sets += 1;
+        i
+    }
+
+    pub fn contains(&self, k: &K) -> bool {
+        self.index.contains_key(k)
+    }
+
+    /// Number of keys
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Number of distinct sets
+    pub fn sets(&self) -> usize {
+        self.sets
+    }
+
+    fn root(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // Point everything on the way straight at the root
+        let mut i = i;
+        while self.parent[i] != root {
+            let next = self.parent[i];
+            self.parent[i] = root;
+            i = next;
+        }
+        root
+    }
+
+    /// The representative key of the set holding k
+    pub fn find(&mut self, k: &K) -> Option<K> {
+        let i = *self.index.get(k)?;
+        let root = self.root(i);
+        Some(self.keys[root].clone())
+    }
+
+    /// Merge the sets holding a and b, adding either if needed.  False if they were already together.
+    pub fn union(&mut self, a: &K, b: &K) -> bool {
+        let a = self.insert(a);
+        let b = self.insert(b);
+        let (a, b) = (self.root(a), self.root(b));
+        if a == b {
+            return false;
+        }
+        let (big, small) = if self.rank[a] >= self.rank[b] { (a, b) } else { (b, a) };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+        if self.rank[big] == self.rank[small] {
+            self.rank[big] += 1;
+        }
+        self.sets -= 1;
+        true
+    }
+
+    /// Are a and b in the same set?  Unknown keys are in no set.
+    pub fn same(&mut self, a: &K, b: &K) -> bool {
+        match (self.index.get(a).copied(), self.index.get(b).copied()) {
+            (Some(a), Some(b)) => self.root(a) == self.root(b),
+            _ => false,
+        }
+    }
+
+    /// Number of keys in the set holding k
+    pub fn set_size(&mut self, k: &K) -> usize {
+        match self.index.get(k).copied() {
+            Some(i) => {
+                let root = self.root(i);
+                self.size[root]
+            }
+            None => 0,
+        }
+    }
+
+    /// All the sets, each as a list of its keys
+    pub fn groups(&mut self) -> Vec<Vec<K>> {
+        let mut groups: HashMap<usize, Vec<K>> = HashMap::new();
+        for i in 0..self.keys.len() {
+            let root = self.root(i);
+            groups.entry(root).or_default().push(self.keys[i].clone());
+        }
+        groups.into_values().collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::Point;
+
+    #[test]
+    fn union_find_basic() {
+        let mut ds = DisjointSet::new();
+        assert!(ds.union(&"a", &"b"));
+        assert!(ds.union(&"c", &"d"));
+        assert!(!ds.union(&"b", &"a"));
+        ds.insert(&"e");
+        assert_eq!(ds.len(), 5);
+        assert_eq!(ds.sets(), 3);
+        assert!(ds.same(&"a", &"b"));
+        assert!(!ds.same(&"a", &"c"));
+        assert!(!ds.same(&"a", &"z"));
+
+        assert!(ds.union(&"a", &"d"));
+        assert_eq!(ds.sets(), 2);
+        assert_eq!(ds.set_size(&"c"), 4);
+        assert_eq!(ds.find(&"b"), ds.find(&"c"));
+        assert_eq!(ds.find(&"z"), None);
+        assert_eq!(ds.groups().iter().map(|g| g.len()).max(), Some(4));
+    }
+
+    #[test]
+    fn union_find_points() {
+        let mut ds = DisjointSet::new();
+        for x in 0..100 {
+            ds.union(&Point::new(x, 0), &Point::new(x + 1, 0));
+        }
+        assert_eq!(ds.sets(), 1);
+        assert_eq!(ds.set_size(&Point::new(50, 0)), 101);
+        assert!(ds.same(&Point::new(0, 0), &Point::new(100, 0)));
+    }
+}
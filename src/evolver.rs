@@ -0,0 +1,299 @@
+use std::{collections::{BTreeMap, HashMap}, hash::Hash};
+use num::{CheckedAdd, CheckedMul, One, Zero};
+use smallvec::SmallVec;
+
+/// The things one member of the population turns into after a single step
+pub type Offspring<K> = SmallVec<[K; 2]>;
+
+/// Anything we can count members with: machine integers (checked) or `num::BigUint` (never overflows)
+pub trait Count: Clone + Zero + One + CheckedAdd + CheckedMul {}
+impl<C: Clone + Zero + One + CheckedAdd + CheckedMul> Count for C {}
+
+/// How to count the population after some number of steps
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Step the whole multiset forward one generation at a time
+    Population,
+    /// Count descendants of each member separately, memoized by (member, depth)
+    Memoized,
+}
+
+/// Something didn't fit while producing the given generation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// The rule couldn't represent some member's offspring
+    Value { generation: usize },
+    /// The number of members doesn't fit in the count type
+    Count { generation: usize },
+}
+
+/// Evolves a multiset of values where every member is independently replaced by its offspring each step.
+/// Members with equal values behave identically, so we only track how many of each value we have.
+/// The rule returns None when the offspring can't be represented in K.
+pub struct CountingEvolver<K, F, C = u64>
+where
+    K: Eq + Hash + Clone,
+    F: Fn(&K) -> Option<Offspring<K>>,
+    C: Count,
+{
+    rule: F,
+
+    // How many members of each value do we have?
+    population: HashMap<K, C>,
+
+    // Total population after each step; history[0] is the initial population
+    history: Vec<C>,
+
+    // Snapshots of the population every `interval` generations, so we can revisit old generations
+    checkpoints: BTreeMap<usize, HashMap<K, C>>,
+    interval: Option<usize>,
+}
+
+fn add<C: Count>(total: &C, count: &C) -> Option<C> {
+    total.checked_add(count)
+}
+
+fn sum<'a, C: Count + 'a>(counts: impl IntoIterator<Item = &'a C>) -> Option<C> {
+    counts.into_iter().try_fold(C::zero(), |total, count| add(&total, count))
+}
+
+impl<K, F, C> CountingEvolver<K, F, C>
+where
+    K: Eq + Hash + Clone,
+    F: Fn(&K) -> Option<Offspring<K>>,
+    C: Count,
+{
+    /// Panics if there are too many seeds to count in C
+    pub fn new(rule: F, seeds: impl IntoIterator<Item = K>) -> Self {
+        let mut population: HashMap<K, C> = HashMap::new();
+        for s in seeds {
+            let count = population.entry(s).or_insert(C::zero());
+            *count = add(count, &C::one()).expect("Too many seeds for the count type");
+        }
+        let history = vec![sum(population.values()).expect("Too many seeds for the count type")];
+        let checkpoints = BTreeMap::from([(0, population.clone())]);
+        CountingEvolver { rule, population, history, checkpoints, interval: None }
+    }
+
+    /// Remember the whole population every `interval` generations
+    pub fn with_checkpoints(mut self, interval: usize) -> Self {
+        assert!(interval > 0);
+        self.interval = Some(interval);
+        self
+    }
+
+    fn next_generation(&self, population: &HashMap<K, C>) -> Result<HashMap<K, C>, Overflow> {
+        let generation = self.generation() + 1;
+        let mut next: HashMap<K, C> = HashMap::new();
+        for (member, count) in population.iter() {
+            for child in (self.rule)(member).ok_or(Overflow::Value { generation })? {
+                let total = next.entry(child).or_insert(C::zero());
+                *total = add(total, count).ok_or(Overflow::Count { generation })?;
+            }
+        }
+        Ok(next)
+    }
+
+    /// Advance the whole population one generation.  On overflow nothing changes.
+    pub fn step(&mut self) -> Result<(), Overflow> {
+        let next = self.next_generation(&self.population)?;
+        let generation = self.generation() + 1;
+        let total = sum(next.values()).ok_or(Overflow::Count { generation })?;
+        self.population = next;
+        self.history.push(total);
+        if self.interval.is_some_and(|interval| generation.is_multiple_of(interval)) {
+            self.checkpoints.insert(generation, self.population.clone());
+        }
+        Ok(())
+    }
+
+    pub fn evolve(&mut self, steps: usize) -> Result<(), Overflow> {
+        for _ in 0..steps {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Number of generations evolved so far
+    pub fn generation(&self) -> usize {
+        self.history.len() - 1
+    }
+
+    pub fn len(&self) -> C {
+        self.history[self.generation()].clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len().is_zero()
+    }
+
+    /// Distinct values and how many members hold each one
+    pub fn population(&self) -> &HashMap<K, C> {
+        &self.population
+    }
+
+    /// Total population after each generation so far, starting with the seeds
+    pub fn history(&self) -> &[C] {
+        &self.history
+    }
+
+    /// Population size at any generation, evolving forward as far as needed
+    pub fn count_at(&mut self, generation: usize) -> Result<C, Overflow> {
+        if generation > self.generation() {
+            self.evolve(generation - self.generation())?;
+        }
+        Ok(self.history[generation].clone())
+    }
+
+    /// The whole population at any generation, replayed from the closest earlier checkpoint
+    pub fn population_at(&mut self, generation: usize) -> Result<HashMap<K, C>, Overflow> {
+        if generation >= self.generation() {
+            self.evolve(generation - self.generation())?;
+            return Ok(self.population.clone());
+        }
+        let (start, snapshot) = self.checkpoints.range(..=generation).next_back().unwrap();
+        let mut replay = CountingEvolver {
+            rule: &self.rule,
+            population: snapshot.clone(),
+            history: self.history[..=*start].to_vec(),
+            checkpoints: BTreeMap::new(),
+            interval: None,
+        };
+        replay.evolve(generation - start)?;
+        Ok(replay.population)
+    }
+
+    /// Population size `steps` generations after the current one, without changing our state
+    pub fn count_after(&self, steps: usize, strategy: Strategy) -> Result<C, Overflow> {
+        match strategy {
+            Strategy::Population => {
+                let mut evolver = CountingEvolver {
+                    rule: &self.rule,
+                    population: self.population.clone(),
+                    history: self.history.clone(),
+                    checkpoints: BTreeMap::new(),
+                    interval: None,
+                };
+                evolver.evolve(steps)?;
+                Ok(evolver.len())
+            }
+            // We can't tell exactly where a memoized count overflowed, so blame the target generation
+            Strategy::Memoized => {
+                let generation = self.generation() + steps;
+                let mut memo = HashMap::new();
+                let mut total = C::zero();
+                for (member, count) in self.population.iter() {
+                    let descendants = self.descendants(member, steps, &mut memo).map_err(|err| match err {
+                        Overflow::Value { .. } => Overflow::Value { generation },
+                        Overflow::Count { .. } => Overflow::Count { generation },
+                    })?;
+                    total = count.checked_mul(&descendants)
+                        .and_then(|n| add(&total, &n))
+                        .ok_or(Overflow::Count { generation })?;
+                }
+                Ok(total)
+            }
+        }
+    }
+
+    // How many members a single `member` becomes after `depth` steps from now.
+    // Errors don't know which generation they belong to yet; count_after fills that in.
+    fn descendants(&self, member: &K, depth: usize, memo: &mut HashMap<(K, usize), C>) -> Result<C, Overflow> {
+        if depth == 0 {
+            return Ok(C::one());
+        }
+        if let Some(count) = memo.get(&(member.clone(), depth)) {
+            return Ok(count.clone());
+        }
+        let mut count = C::zero();
+        for child in (self.rule)(member).ok_or(Overflow::Value { generation: 0 })? {
+            let n = self.descendants(&child, depth - 1, memo)?;
+            count = add(&count, &n).ok_or(Overflow::Count { generation: 0 })?;
+        }
+        memo.insert((member.clone(), depth), count.clone());
+        Ok(count)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::BigUint;
+    use smallvec::smallvec;
+
+    // Every cell splits in two; odd cells also leave a spore behind
+    fn rule(k: &u32) -> Option<Offspring<u32>> {
+        if k % 2 == 1 {
+            Some(smallvec![k + 1, k + 1, 0])
+        } else {
+            Some(smallvec![k + 1, k / 2])
+        }
+    }
+
+    #[test]
+    fn history_tracks_each_step() {
+        let mut evolver: CountingEvolver<_, _> = CountingEvolver::new(rule, [1, 1, 2]);
+        assert_eq!(evolver.len(), 3);
+        evolver.evolve(3).unwrap();
+        assert_eq!(evolver.generation(), 3);
+        assert_eq!(evolver.history().len(), 4);
+        assert_eq!(evolver.history()[0], 3);
+        assert_eq!(*evolver.history().last().unwrap(), evolver.len());
+    }
+
+    #[test]
+    fn strategies_agree() {
+        let evolver: CountingEvolver<_, _> = CountingEvolver::new(rule, [0, 3, 3, 7]);
+        for steps in 0..12 {
+            assert_eq!(
+                evolver.count_after(steps, Strategy::Population),
+                evolver.count_after(steps, Strategy::Memoized)
+            );
+        }
+    }
+
+    #[test]
+    fn count_after_keeps_state() {
+        let mut evolver: CountingEvolver<_, _> = CountingEvolver::new(rule, [5]);
+        let expect = evolver.count_after(4, Strategy::Memoized).unwrap();
+        assert_eq!(evolver.generation(), 0);
+        evolver.evolve(4).unwrap();
+        assert_eq!(evolver.len(), expect);
+    }
+
+    #[test]
+    fn overflow_is_reported() {
+        let mut evolver: CountingEvolver<_, _, u8> = CountingEvolver::new(rule, [1]);
+        let err = evolver.evolve(20).unwrap_err();
+        let Overflow::Count { generation } = err else { panic!("{:?}", err) };
+        assert_eq!(evolver.generation(), generation - 1);
+        assert_eq!(evolver.count_after(20, Strategy::Memoized).unwrap_err(), Overflow::Count { generation: generation - 1 + 20 });
+
+        let mut big: CountingEvolver<_, _, BigUint> = CountingEvolver::new(rule, [1]);
+        big.evolve(generation).unwrap();
+        assert!(big.len() > BigUint::from(u8::MAX));
+    }
+
+    #[test]
+    fn value_overflow_is_reported() {
+        let mut evolver: CountingEvolver<_, _> = CountingEvolver::new(|k: &u32| u32::checked_mul(*k, 1000).map(|k| smallvec![k]), [7]);
+        assert_eq!(evolver.evolve(5), Err(Overflow::Value { generation: 3 }));
+        assert_eq!(evolver.generation(), 2);
+    }
+
+    #[test]
+    fn checkpoints() {
+        let mut evolver: CountingEvolver<_, _> = CountingEvolver::new(rule, [2, 9]).with_checkpoints(4);
+        assert_eq!(evolver.count_at(10).unwrap(), evolver.len());
+        let at_6 = evolver.population_at(6).unwrap();
+        assert_eq!(evolver.generation(), 10);
+        assert_eq!(at_6.values().sum::<u64>(), evolver.history()[6]);
+
+        let mut fresh: CountingEvolver<_, _> = CountingEvolver::new(rule, [2, 9]);
+        fresh.evolve(6).unwrap();
+        assert_eq!(&at_6, fresh.population());
+        assert_eq!(fresh.population_at(1).unwrap().values().sum::<u64>(), fresh.history()[1]);
+        assert_eq!(evolver.count_at(3).unwrap(), fresh.history()[3]);
+    }
+}
@@ -0,0 +1,598 @@
+use std::collections::{BTreeSet, VecDeque};
+use itertools::Itertools;
+use crate::{day22::prng_inverse, harness::Rng, point::Point};
+
+/// A random puzzle input, along with whatever answer the generator planted in it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Generated<A> {
+    pub input: String,
+    pub answer: A,
+}
+
+// A blank maze: `width` by `height` rooms at odd coordinates, with walls everywhere else.
+// Rows run top to bottom.
+fn walled(width: usize, height: usize) -> Vec<Vec<char>> {
+    vec![vec!['#'; width * 2 + 1]; height * 2 + 1]
+}
+
+fn render(cells: &[Vec<char>]) -> String {
+    cells.iter().map(|row| row.iter().collect::<String>()).join("\n")
+}
+
+// Rooms next to a room, as (room, wall between)
+fn rooms_around(cells: &[Vec<char>], (r, c): (usize, usize)) -> Vec<((usize, usize), (usize, usize))> {
+    let (height, width) = (cells.len(), cells[0].len());
+    let mut around = Vec::new();
+    if r >= 3 {
+        around.push(((r - 2, c), (r - 1, c)));
+    }
+    if r + 2 < height {
+        around.push(((r + 2, c), (r + 1, c)));
+    }
+    if c >= 3 {
+        around.push(((r, c - 2), (r, c - 1)));
+    }
+    if c + 2 < width {
+        around.push(((r, c + 2), (r, c + 1)));
+    }
+    around
+}
+
+/// A day20 racetrack: a single corridor with no branches from S to E.
+/// The answer is the number of picoseconds the honest race takes.
+pub fn racetrack(seed: u64, width: usize, height: usize) -> Generated<usize> {
+    assert!(width * height >= 2);
+    let mut rng = Rng::new(seed);
+    loop {
+        let mut cells = walled(width, height);
+        let start = (rng.below(height) * 2 + 1, rng.below(width) * 2 + 1);
+        let mut track = vec![start];
+        cells[start.0][start.1] = '.';
+        // Wander into unvisited rooms until we're boxed in
+        loop {
+            let here = *track.last().unwrap();
+            let open: Vec<_> = rooms_around(&cells, here).into_iter()
+                .filter(|(room, _)| cells[room.0][room.1] == '#')
+                .collect();
+            if open.is_empty() {
+                break;
+            }
+            let (room, wall) = *rng.pick(&open);
+            cells[wall.0][wall.1] = '.';
+            cells[room.0][room.1] = '.';
+            track.push(wall);
+            track.push(room);
+        }
+        // Boxed in too early; try again
+        if track.len() < width * height / 2 {
+            continue;
+        }
+        let end = *track.last().unwrap();
+        cells[start.0][start.1] = 'S';
+        cells[end.0][end.1] = 'E';
+        return Generated { input: render(&cells), answer: track.len() - 1 };
+    }
+}
+
+// A perfect maze, carved depth first from `start`: there's exactly one route between any two rooms
+fn perfect_maze(rng: &mut Rng, width: usize, height: usize, start: (usize, usize)) -> Vec<Vec<char>> {
+    let mut cells = walled(width, height);
+    cells[start.0][start.1] = '.';
+    let mut stack = vec![start];
+    while let Some(here) = stack.last().copied() {
+        let open: Vec<_> = rooms_around(&cells, here).into_iter()
+            .filter(|(room, _)| cells[room.0][room.1] == '#')
+            .collect();
+        if open.is_empty() {
+            stack.pop();
+            continue;
+        }
+        let (room, wall) = *rng.pick(&open);
+        cells[wall.0][wall.1] = '.';
+        cells[room.0][room.1] = '.';
+        stack.push(room);
+    }
+    cells
+}
+
+// The cells from start to end, inclusive, along the shortest way through a walled maze
+fn maze_route(cells: &[Vec<char>], start: (usize, usize), end: (usize, usize)) -> Vec<(usize, usize)> {
+    let mut parent = vec![vec![None; cells[0].len()]; cells.len()];
+    let mut queue = VecDeque::from([start]);
+    while let Some((r, c)) = queue.pop_front() {
+        for (nr, nc) in [(r - 1, c), (r + 1, c), (r, c - 1), (r, c + 1)] {
+            if cells[nr][nc] != '#' && parent[nr][nc].is_none() && (nr, nc) != start {
+                parent[nr][nc] = Some((r, c));
+                queue.push_back((nr, nc));
+            }
+        }
+    }
+    let mut route = vec![end];
+    while let Some(prev) = parent[route.last().unwrap().0][route.last().unwrap().1] {
+        route.push(prev);
+    }
+    route.reverse();
+    route
+}
+
+/// A day16 reindeer maze with exactly one route from S (bottom left) to E (top right), so the
+/// answer is known: (lowest score, tiles on the best path).  The reindeer starts facing east.
+pub fn reindeer_maze(seed: u64, width: usize, height: usize) -> Generated<(usize, usize)> {
+    let mut rng = Rng::new(seed);
+    let start = (height * 2 - 1, 1);
+    let end = (1, width * 2 - 1);
+    let mut cells = perfect_maze(&mut rng, width, height, start);
+    let route = maze_route(&cells, start, end);
+
+    // Rows run top to bottom, so east is (0, 1)
+    let mut facing = (0, 1);
+    let mut score = 0;
+    for (a, b) in route.iter().tuple_windows() {
+        let step = (b.0 as i32 - a.0 as i32, b.1 as i32 - a.1 as i32);
+        if step != facing {
+            score += if step == (-facing.0, -facing.1) { 2000 } else { 1000 };
+            facing = step;
+        }
+        score += 1;
+    }
+    cells[start.0][start.1] = 'S';
+    cells[end.0][end.1] = 'E';
+    Generated { input: render(&cells), answer: (score, route.len()) }
+}
+
+/// A day8 antenna map with `frequencies` different frequencies of 2 to 4 antennas each.
+/// There's no planted answer.
+pub fn antennas(seed: u64, width: usize, height: usize, frequencies: usize) -> Generated<()> {
+    let names: Vec<char> = ('0'..='9').chain('a'..='z').chain('A'..='Z').collect();
+    assert!(frequencies <= names.len() && frequencies * 4 <= width * height);
+    let mut rng = Rng::new(seed);
+    let mut cells = vec![vec!['.'; width]; height];
+    let mut free: Vec<(usize, usize)> = (0..height).cartesian_product(0..width).collect();
+    rng.shuffle(&mut free);
+    let mut names = names;
+    rng.shuffle(&mut names);
+    for name in names.iter().take(frequencies) {
+        for _ in 0..rng.between(2, 4) {
+            let (r, c) = free.pop().unwrap();
+            cells[r][c] = *name;
+        }
+    }
+    Generated { input: render(&cells), answer: () }
+}
+
+/// A day9 disk map in the puzzle's style: `files` files of 1 to 9 blocks with gaps of 0 to 9 between.
+/// There's no planted answer.
+pub fn disk_map(seed: u64, files: usize) -> Generated<()> {
+    assert!(files > 0);
+    let mut rng = Rng::new(seed);
+    let input = (0..files * 2 - 1)
+        .map(|i| if i % 2 == 0 { rng.between(1, 9) } else { rng.between(0, 9) })
+        .map(|d| char::from_digit(d as u32, 10).unwrap())
+        .collect();
+    Generated { input, answer: () }
+}
+
+/// A day10 map of `trails` separate trails from 0 to 9 on impassable ground, none touching another,
+/// so each head reaches one summit by one route.  The answer is (total score, total rating).
+/// Fewer trails are laid if the map is too crowded to fit them all.
+pub fn trail_map(seed: u64, width: usize, height: usize, trails: usize) -> Generated<(usize, usize)> {
+    let mut rng = Rng::new(seed);
+    let mut cells = vec![vec!['.'; width]; height];
+    let around = |(r, c): (usize, usize)| {
+        [(r.wrapping_sub(1), c), (r + 1, c), (r, c.wrapping_sub(1)), (r, c + 1)].into_iter()
+            .filter(|(r, c)| *r < height && *c < width)
+            .collect::<Vec<_>>()
+    };
+    let mut laid = 0;
+    for _ in 0..trails * 20 {
+        if laid == trails {
+            break;
+        }
+        // Each step goes somewhere empty that doesn't touch another trail
+        let mut trail = vec![(rng.below(height), rng.below(width))];
+        let usable = |cells: &[Vec<char>], trail: &[(usize, usize)], p: (usize, usize)| {
+            cells[p.0][p.1] == '.' && !trail.contains(&p)
+                && around(p).iter().all(|n| cells[n.0][n.1] == '.' || trail.contains(n))
+        };
+        if !usable(&cells, &[], trail[0]) {
+            continue;
+        }
+        while trail.len() < 10 {
+            let next: Vec<_> = around(*trail.last().unwrap()).into_iter()
+                .filter(|p| usable(&cells, &trail, *p))
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            trail.push(*rng.pick(&next));
+        }
+        if trail.len() == 10 {
+            for (height, (r, c)) in trail.into_iter().enumerate() {
+                cells[r][c] = char::from_digit(height as u32, 10).unwrap();
+            }
+            laid += 1;
+        }
+    }
+    Generated { input: render(&cells), answer: (laid, laid) }
+}
+
+/// A line of day11 stones: zeros, small numbers and big ones, with both odd and even digit counts.
+/// There's no planted answer.
+pub fn stones(seed: u64, count: usize) -> Generated<()> {
+    let mut rng = Rng::new(seed);
+    let stones = (0..count)
+        .map(|_| match rng.below(4) {
+            0 => 0,
+            1 => rng.between(1, 99),
+            2 => rng.between(100, 99_999),
+            _ => rng.between(100_000, 99_999_999),
+        })
+        .join(" ");
+    Generated { input: stones, answer: () }
+}
+
+/// A day12 garden cut into rectangular plots, with no two touching plots growing the same plant.
+/// The answer is (price by perimeter, price by sides), which for rectangles is
+/// (area times twice width plus height, area times 4).
+pub fn garden(seed: u64, width: usize, height: usize) -> Generated<(usize, usize)> {
+    let mut rng = Rng::new(seed);
+
+    // Keep cutting rectangles in two, stopping at random once they're small enough.  A plot with
+    // w + h <= 12 touches at most 24 cells, so there's always a letter left for it, whatever the size.
+    let mut plots = Vec::new();
+    let mut uncut = vec![(0, 0, width, height)];
+    while let Some((x, y, w, h)) = uncut.pop() {
+        if w * h == 1 || (w * h <= 12 && w + h <= 12 && rng.one_in(3)) {
+            plots.push((x, y, w, h));
+        } else if w > 1 && (h == 1 || rng.one_in(2)) {
+            let cut = rng.between(1, w - 1);
+            uncut.push((x, y, cut, h));
+            uncut.push((x + cut, y, w - cut, h));
+        } else {
+            let cut = rng.between(1, h - 1);
+            uncut.push((x, y, w, cut));
+            uncut.push((x, y + cut, w, h - cut));
+        }
+    }
+
+    let mut plot_at = vec![vec![0; width]; height];
+    for (i, (x, y, w, h)) in plots.iter().enumerate() {
+        for (r, c) in (*y..y + h).cartesian_product(*x..x + w) {
+            plot_at[r][c] = i;
+        }
+    }
+    let mut plants: Vec<Option<char>> = vec![None; plots.len()];
+    let mut letters: Vec<char> = ('A'..='Z').collect();
+    for (i, (x, y, w, h)) in plots.iter().enumerate() {
+        let mut taken = BTreeSet::new();
+        for (r, c) in (y.saturating_sub(1)..(y + h + 1).min(height)).cartesian_product(*x..x + w)
+            .chain((*y..y + h).cartesian_product(x.saturating_sub(1)..(x + w + 1).min(width)))
+        {
+            taken.extend(plants[plot_at[r][c]]);
+        }
+        rng.shuffle(&mut letters);
+        plants[i] = Some(*letters.iter().find(|l| !taken.contains(*l)).unwrap());
+    }
+
+    let cells: Vec<Vec<char>> = plot_at.iter()
+        .map(|row| row.iter().map(|i| plants[*i].unwrap()).collect())
+        .collect();
+    let by_perimeter = plots.iter().map(|(_, _, w, h)| w * h * 2 * (w + h)).sum();
+    let by_sides = plots.iter().map(|(_, _, w, h)| w * h * 4).sum();
+    Generated { input: render(&cells), answer: (by_perimeter, by_sides) }
+}
+
+/// Day14 robots on a `width` by `height` field, each placed in a quadrant after 100 seconds and then
+/// walked back to where it starts.  The answer is the safety factor after 100 seconds.
+pub fn robots(seed: u64, count: usize, width: usize, height: usize) -> Generated<usize> {
+    assert!(width >= 3 && height >= 3);
+    let mut rng = Rng::new(seed);
+    let mut quadrants = [0; 4];
+    let mut lines = Vec::new();
+    for _ in 0..count {
+        // Halves leave out the middle row or column when there is one
+        let (right, bottom) = (rng.one_in(2), rng.one_in(2));
+        quadrants[bottom as usize * 2 + right as usize] += 1;
+        let x = if right { rng.between(width.div_ceil(2), width - 1) } else { rng.below(width / 2) };
+        let y = if bottom { rng.between(height.div_ceil(2), height - 1) } else { rng.below(height / 2) };
+        let vx = rng.between(0, 2 * width - 2) as i64 - (width as i64 - 1);
+        let vy = rng.between(0, 2 * height - 2) as i64 - (height as i64 - 1);
+        let px = (x as i64 - 100 * vx).rem_euclid(width as i64);
+        let py = (y as i64 - 100 * vy).rem_euclid(height as i64);
+        lines.push(format!("p={},{} v={},{}", px, py, vx, vy));
+    }
+    Generated { input: lines.join("\n"), answer: quadrants.iter().product() }
+}
+
+/// Day18 bytes falling on a `size` by `size` space (`size` odd).  Everything off one winding route
+/// falls first, then a byte lands on the route, then some more of the route.
+/// The answer is (bytes off the route, steps along it, the byte that cuts it).
+pub fn falling_bytes(seed: u64, size: usize) -> Generated<(usize, usize, Point)> {
+    assert!(size >= 3 && size % 2 == 1);
+    let mut rng = Rng::new(seed);
+    let rooms = size.div_ceil(2);
+    let walled = perfect_maze(&mut rng, rooms, rooms, (1, 1));
+    // Bytes may land anywhere in the space, so drop the outer wall
+    let route: Vec<Point> = maze_route(&walled, (1, 1), (size, size)).into_iter()
+        .map(|(r, c)| Point::new(c as i32 - 1, r as i32 - 1))
+        .collect();
+
+    let mut off: Vec<Point> = (0..size as i32).cartesian_product(0..size as i32)
+        .map(|(x, y)| Point::new(x, y))
+        .filter(|p| !route.contains(p))
+        .collect();
+    rng.shuffle(&mut off);
+    let mut on = route[1..route.len() - 1].to_vec();
+    rng.shuffle(&mut on);
+    let fallen = off.len();
+    let cut = on[0];
+    let more = rng.below(on.len()) + 1;
+    let bytes = off.iter().chain(on[..more].iter()).map(|p| format!("{},{}", p.x, p.y)).join("\n");
+    Generated { input: bytes, answer: (fallen, route.len() - 1, cut) }
+}
+
+/// Day21 door codes: three digits and an A.  There's no planted answer.
+pub fn door_codes(seed: u64, count: usize) -> Generated<()> {
+    let mut rng = Rng::new(seed);
+    let codes = (0..count).map(|_| format!("{:03}A", rng.below(1000))).join("\n");
+    Generated { input: codes, answer: () }
+}
+
+/// Day22 buyers' first secrets, worked backwards from a random 2000th secret for each.
+/// The answer is the sum of the 2000th secrets.
+pub fn secrets(seed: u64, buyers: usize) -> Generated<usize> {
+    let mut rng = Rng::new(seed);
+    let mut total = 0;
+    let mut lines = Vec::new();
+    for _ in 0..buyers {
+        let last = rng.below(1 << 24) as u32;
+        total += last as usize;
+        let first = (0..2000).fold(last, |x, _| prng_inverse(x));
+        lines.push(first.to_string());
+    }
+    Generated { input: lines.join("\n"), answer: total }
+}
+
+/// A day15 warehouse and its moves, with some walls inside.  There's no planted answer.
+pub fn warehouse(seed: u64, width: usize, height: usize, boxes: usize, moves: usize) -> Generated<()> {
+    assert!(width >= 3 && height >= 3);
+    let mut rng = Rng::new(seed);
+    let mut cells = vec![vec!['.'; width]; height];
+    for (r, row) in cells.iter_mut().enumerate() {
+        for (c, cell) in row.iter_mut().enumerate() {
+            if r == 0 || c == 0 || r == height - 1 || c == width - 1 || rng.one_in(12) {
+                *cell = '#';
+            }
+        }
+    }
+    let mut free: Vec<(usize, usize)> = (0..height)
+        .cartesian_product(0..width)
+        .filter(|(r, c)| cells[*r][*c] == '.')
+        .collect();
+    assert!(free.len() > boxes, "No room for the robot");
+    rng.shuffle(&mut free);
+    let (r, c) = free[0];
+    cells[r][c] = '@';
+    for (r, c) in free[1..=boxes].iter() {
+        cells[*r][*c] = 'O';
+    }
+    let arrows = ['^', 'v', '<', '>'];
+    let moves: Vec<char> = (0..moves).map(|_| *rng.pick(&arrows)).collect();
+    let moves = moves.chunks(70).map(|line| line.iter().collect::<String>()).join("\n");
+    Generated { input: format!("{}\n\n{}", render(&cells), moves), answer: () }
+}
+
+/// Day19 towels and designs.  Towels never use green stripes, so the designs we plant a green stripe
+/// in are impossible and the rest are made of towels.  The answer is how many designs are possible.
+/// Towels are 1 to 8 stripes long, so there can't be more than 87380 of them.
+pub fn towels(seed: u64, towels: usize, designs: usize) -> Generated<usize> {
+    let mut rng = Rng::new(seed);
+    let stripes = ['w', 'u', 'b', 'r'];
+    assert!(towels <= (1..=8).map(|k| 4usize.pow(k)).sum(), "Not that many different towels");
+    let mut set = BTreeSet::new();
+    while set.len() < towels {
+        set.insert((0..rng.between(1, 8)).map(|_| *rng.pick(&stripes)).collect::<String>());
+    }
+    let list: Vec<&String> = set.iter().collect();
+    let mut possible = 0;
+    let designs = (0..designs)
+        .map(|_| {
+            let mut design: String = (0..rng.between(1, 8)).map(|_| rng.pick(&list).as_str()).collect();
+            if rng.one_in(3) {
+                design.insert(rng.below(design.len() + 1), 'g');
+            } else {
+                possible += 1;
+            }
+            design
+        })
+        .join("\n");
+    Generated { input: format!("{}\n\n{}", set.iter().join(", "), designs), answer: possible }
+}
+
+/// A day23 network with a planted clique of `party` computers and `noise` other connections that
+/// can't form a triangle.  The answer is (triangles with a t computer, the LAN party password).
+pub fn lan(seed: u64, computers: usize, party: usize, noise: usize) -> Generated<(usize, String)> {
+    assert!(party >= 3 && computers >= party + 2);
+    let mut rng = Rng::new(seed);
+    let mut names = BTreeSet::new();
+    while names.len() < computers {
+        let letter = |rng: &mut Rng| (b'a' + rng.below(26) as u8) as char;
+        names.insert(format!("{}{}", letter(&mut rng), letter(&mut rng)));
+    }
+    let mut names: Vec<String> = names.into_iter().collect();
+    rng.shuffle(&mut names);
+    let (clique, rest) = names.split_at(party);
+    let (left, right) = rest.split_at(rest.len() / 2);
+
+    let mut edges = BTreeSet::new();
+    for [a, b] in clique.iter().array_combinations() {
+        edges.insert((a.clone(), b.clone()));
+    }
+    // Only left-right noise, so it's bipartite, and each left computer knows at most one of the party
+    for _ in 0..noise {
+        edges.insert((rng.pick(left).clone(), rng.pick(right).clone()));
+    }
+    for l in left.iter() {
+        if rng.one_in(2) {
+            edges.insert((l.clone(), rng.pick(clique).clone()));
+        }
+    }
+
+    let mut lines: Vec<String> = edges.into_iter()
+        .map(|(a, b)| if rng.one_in(2) { format!("{}-{}", a, b) } else { format!("{}-{}", b, a) })
+        .collect();
+    lines.sort();
+    rng.shuffle(&mut lines);
+
+    let choose3 = |n: usize| n * n.saturating_sub(1) * n.saturating_sub(2) / 6;
+    let others = clique.iter().filter(|n| !n.starts_with('t')).count();
+    let triangles = choose3(party) - choose3(others);
+    let password = clique.iter().sorted().join(",");
+    Generated { input: lines.join("\n"), answer: (triangles, password) }
+}
+
+/// Day13 claw machines, each winnable with at most 100 presses of each button, except for about a
+/// quarter whose prize has been nudged out of reach.  The answer is the fewest tokens to win every
+/// winnable prize.
+pub fn claw_machines(seed: u64, machines: usize) -> Generated<usize> {
+    let mut rng = Rng::new(seed);
+    let mut tokens = 0;
+    let mut blocks = Vec::new();
+    while blocks.len() < machines {
+        let (ax, ay, bx, by) = (rng.between(10, 99), rng.between(10, 99), rng.between(10, 99), rng.between(10, 99));
+        // Buttons pointing the same way would give more than one way to win
+        if ax * by == ay * bx {
+            continue;
+        }
+        let (a, b) = (rng.between(0, 100), rng.between(0, 100));
+        let (mut px, py) = (a * ax + b * bx, a * ay + b * by);
+        let winnable = !rng.one_in(4);
+        if !winnable {
+            px += 1;
+            if presses((ax, ay), (bx, by), (px, py)).is_some() {
+                continue;
+            }
+        }
+        if winnable {
+            tokens += 3 * a + b;
+        }
+        blocks.push(format!("Button A: X+{}, Y+{}\nButton B: X+{}, Y+{}\nPrize: X={}, Y={}", ax, ay, bx, by, px, py));
+    }
+    Generated { input: blocks.join("\n\n"), answer: tokens }
+}
+
+// Presses of A and B that land exactly on the prize, if there's a whole, non-negative way to
+fn presses(a: (usize, usize), b: (usize, usize), prize: (usize, usize)) -> Option<(usize, usize)> {
+    let (ax, ay, bx, by) = (a.0 as i64, a.1 as i64, b.0 as i64, b.1 as i64);
+    let (px, py) = (prize.0 as i64, prize.1 as i64);
+    let det = ax * by - ay * bx;
+    let (na, nb) = (px * by - py * bx, ax * py - ay * px);
+    if det == 0 || na % det != 0 || nb % det != 0 || na / det < 0 || nb / det < 0 {
+        return None;
+    }
+    Some(((na / det) as usize, (nb / det) as usize))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_neighbors(cells: &[Vec<char>], r: usize, c: usize) -> usize {
+        [(r - 1, c), (r + 1, c), (r, c - 1), (r, c + 1)].iter()
+            .filter(|(r, c)| cells[*r][*c] != '#')
+            .count()
+    }
+
+    #[test]
+    fn seeds_are_reproducible() {
+        assert_eq!(racetrack(3, 10, 8), racetrack(3, 10, 8));
+        assert_ne!(racetrack(3, 10, 8), racetrack(4, 10, 8));
+        assert_eq!(lan(5, 40, 6, 60), lan(5, 40, 6, 60));
+        assert_eq!(warehouse(1, 10, 8, 10, 100), warehouse(1, 10, 8, 10, 100));
+        assert_eq!(garden(2, 12, 9), garden(2, 12, 9));
+        let big = garden(9, 200, 150);
+        assert_eq!(big.input.lines().count(), 150);
+        assert!(big.input.lines().all(|l| l.len() == 200 && l.chars().all(|c| c.is_ascii_uppercase())));
+        assert_eq!(falling_bytes(6, 9), falling_bytes(6, 9));
+        assert_ne!(secrets(7, 5), secrets(8, 5));
+    }
+
+    #[test]
+    fn trails_fit() {
+        for seed in 0..10 {
+            let map = trail_map(seed, 30, 20, 12);
+            assert_eq!(map.answer, (12, 12));
+            assert_eq!(map.input.matches('0').count(), 12);
+            assert_eq!(map.input.matches('9').count(), 12);
+        }
+        // Too crowded for more than a few
+        assert!(trail_map(1, 5, 5, 10).answer.0 < 10);
+    }
+
+    #[test]
+    fn racetracks_never_branch() {
+        for seed in 0..20 {
+            let track = racetrack(seed, 12, 9);
+            let cells: Vec<Vec<char>> = track.input.lines().map(|l| l.chars().collect()).collect();
+            let mut ends = 0;
+            let mut open = 0;
+            for r in 1..cells.len() - 1 {
+                for c in 1..cells[0].len() - 1 {
+                    if cells[r][c] != '#' {
+                        open += 1;
+                        match open_neighbors(&cells, r, c) {
+                            1 => ends += 1,
+                            2 => {}
+                            n => panic!("{} ways out of ({}, {})", n, r, c),
+                        }
+                    }
+                }
+            }
+            assert_eq!(ends, 2);
+            assert_eq!(open, track.answer + 1);
+        }
+    }
+
+    #[test]
+    fn maze_answer_matches_dijkstra() {
+        for seed in 0..10 {
+            let maze = reindeer_maze(seed, 8, 6);
+            let cells: Vec<Vec<char>> = maze.input.lines().map(|l| l.chars().collect()).collect();
+            let start = ((cells.len() - 2) as i32, 1, 0, 1);
+            let best = pathfinding::prelude::dijkstra(
+                &start,
+                |&(r, c, dr, dc)| {
+                    let mut next = vec![((r, c, dc, -dr), 1000), ((r, c, -dc, dr), 1000)];
+                    if cells[(r + dr) as usize][(c + dc) as usize] != '#' {
+                        next.push(((r + dr, c + dc, dr, dc), 1));
+                    }
+                    next
+                },
+                |&(r, c, _, _)| cells[r as usize][c as usize] == 'E',
+            ).unwrap();
+            assert_eq!(best.1, maze.answer.0);
+        }
+    }
+
+    #[test]
+    fn towels_and_claws() {
+        let t = towels(8, 10, 20);
+        assert!(!t.input.split_once("\n\n").unwrap().0.contains('g'));
+        let impossible = t.input.split_once("\n\n").unwrap().1.lines().filter(|d| d.contains('g')).count();
+        assert_eq!(t.answer + impossible, 20);
+        // As many towels as asked for, all different
+        assert_eq!(towels(3, 20, 0).input.split_once("\n\n").unwrap().0.split(", ").count(), 20);
+
+        assert_eq!(presses((94, 34), (22, 67), (8400, 5400)), Some((80, 40)));
+        assert_eq!(presses((26, 66), (67, 21), (12748, 12176)), None);
+        let claws = claw_machines(2, 30);
+        assert_eq!(claws.input.split("\n\n").count(), 30);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not that many different towels")]
+    fn towels_run_out() {
+        towels(1, 87381, 1);
+    }
+}
@@ -0,0 +1,487 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+pub type NodeId = u16;
+
+/// An undirected graph of named nodes.  Names are interned to small ids, and each node keeps a sorted list of its neighbors.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Graph {
+    names: Vec<String>,
+    ids: HashMap<String, NodeId>,
+    adj: Vec<Vec<NodeId>>,
+}
+
+// Items in both sorted lists
+fn intersect(a: &[NodeId], b: &[NodeId]) -> Vec<NodeId> {
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                out.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out
+}
+
+impl Graph {
+    pub fn new() -> Graph {
+        Graph::default()
+    }
+
+    pub fn from_edges<'a>(edges: impl IntoIterator<Item = (&'a str, &'a str)>) -> Graph {
+        let mut graph = Graph::new();
+        for (a, b) in edges {
+            graph.add_edge(a, b);
+        }
+        graph
+    }
+
+    /// Build from a map of each node to its neighbors
+    pub fn from_map(map: &HashMap<String, HashSet<String>>) -> Graph {
+        let mut graph = Graph::new();
+        // Intern in name order so ids don't depend on hash order
+        let mut names: Vec<&String> = map.keys().collect();
+        names.sort();
+        for name in names {
+            graph.intern(name);
+            for other in map[name].iter() {
+                graph.add_edge(name, other);
+            }
+        }
+        graph
+    }
+
+    /// The id for a name, adding the node if it's new
+    pub fn intern(&mut self, name: &str) -> NodeId {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = NodeId::try_from(self.names.len()).expect("Too many nodes");
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.adj.push(Vec::new());
+        id
+    }
+
+    pub fn add_edge(&mut self, a: &str, b: &str) {
+        let (a, b) = (self.intern(a), self.intern(b));
+        if a == b {
+            return;
+        }
+        for (from, to) in [(a, b), (b, a)] {
+            if let Err(i) = self.adj[from as usize].binary_search(&to) {
+                self.adj[from as usize].insert(i, to);
+            }
+        }
+    }
+
+    pub fn id(&self, name: &str) -> Option<NodeId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: NodeId) -> &str {
+        &self.names[id as usize]
+    }
+
+    /// Number of nodes
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = NodeId> {
+        0..self.len() as NodeId
+    }
+
+    /// Neighbors of a node, in id order
+    pub fn neighbors(&self, id: NodeId) -> &[NodeId] {
+        &self.adj[id as usize]
+    }
+
+    pub fn connected(&self, a: NodeId, b: NodeId) -> bool {
+        self.adj[a as usize].binary_search(&b).is_ok()
+    }
+
+    /// Sorted names of a group of nodes, for printing
+    pub fn names(&self, nodes: &[NodeId]) -> Vec<&str> {
+        let mut names: Vec<&str> = nodes.iter().map(|id| self.name(*id)).collect();
+        names.sort();
+        names
+    }
+
+    /// Every set of three mutually connected nodes, each listed once in id order
+    pub fn triangles(&self) -> Vec<[NodeId; 3]> {
+        let mut triangles = Vec::new();
+        for a in self.nodes() {
+            let later: Vec<NodeId> = self.neighbors(a).iter().copied().filter(|b| *b > a).collect();
+            for (i, b) in later.iter().enumerate() {
+                for c in later[i + 1..].iter() {
+                    if self.connected(*b, *c) {
+                        triangles.push([a, *b, *c]);
+                    }
+                }
+            }
+        }
+        triangles
+    }
+
+    // Bron-Kerbosch: r is the clique so far, p the nodes that could join it, x the ones already tried.
+    // Pivot on the node with most neighbors in p; any maximal clique must include it or a non-neighbor of it.
+    fn bron_kerbosch(&self, r: &mut Vec<NodeId>, mut p: Vec<NodeId>, mut x: Vec<NodeId>, found: &mut dyn FnMut(&[NodeId])) {
+        if p.is_empty() {
+            if x.is_empty() {
+                found(r);
+            }
+            return;
+        }
+        let pivot = p.iter().chain(x.iter())
+            .copied()
+            .max_by_key(|u| intersect(&p, self.neighbors(*u)).len())
+            .unwrap();
+        let candidates: Vec<NodeId> = p.iter().copied().filter(|v| !self.connected(pivot, *v)).collect();
+        for v in candidates {
+            let near = self.neighbors(v);
+            r.push(v);
+            self.bron_kerbosch(r, intersect(&p, near), intersect(&x, near), found);
+            r.pop();
+            p.retain(|u| *u != v);
+            if let Err(i) = x.binary_search(&v) {
+                x.insert(i, v);
+            }
+        }
+    }
+
+    /// Every clique that can't be grown any further
+    pub fn maximal_cliques(&self) -> Vec<Vec<NodeId>> {
+        let mut cliques = Vec::new();
+        self.bron_kerbosch(&mut Vec::new(), self.nodes().collect(), Vec::new(), &mut |c| {
+            let mut c = c.to_vec();
+            c.sort();
+            cliques.push(c);
+        });
+        cliques
+    }
+
+    // Branch and bound: r is the clique so far, p the later nodes that could still join it.
+    // A branch is dropped as soon as even taking all of p couldn't match the best clique so far.
+    fn grow_clique(&self, r: &mut Vec<NodeId>, p: &[NodeId], best: &mut Option<(Vec<NodeId>, String)>) {
+        let best_len = |best: &Option<(Vec<NodeId>, String)>| best.as_ref().map_or(0, |(c, _)| c.len());
+        if p.is_empty() {
+            if r.len() >= best_len(best) {
+                let mut clique = r.clone();
+                clique.sort();
+                let names = self.names(&clique).join(",");
+                if best.as_ref().is_none_or(|(c, n)| clique.len() > c.len() || names < *n) {
+                    *best = Some((clique, names));
+                }
+            }
+            return;
+        }
+        for (i, v) in p.iter().enumerate() {
+            if r.len() + p.len() - i < best_len(best) {
+                return;
+            }
+            r.push(*v);
+            self.grow_clique(r, &intersect(&p[i + 1..], self.neighbors(*v)), best);
+            r.pop();
+        }
+    }
+
+    /// The largest clique that includes some node whose name passes the test.
+    /// Ties go to the clique whose sorted names come first.
+    pub fn maximum_clique_where(&self, test: impl Fn(&str) -> bool) -> Option<Vec<NodeId>> {
+        let mut best = None;
+        for v in self.nodes().filter(|v| test(self.name(*v))) {
+            self.grow_clique(&mut vec![v], self.neighbors(v), &mut best);
+        }
+        best.map(|(clique, _)| clique)
+    }
+
+    /// The largest clique in the graph
+    pub fn maximum_clique(&self) -> Option<Vec<NodeId>> {
+        self.maximum_clique_where(|_| true)
+    }
+}
+
+// Analytics
+impl Graph {
+    /// Groups of nodes that can reach each other, largest first
+    pub fn components(&self) -> Vec<Vec<NodeId>> {
+        let mut seen = vec![false; self.len()];
+        let mut components = Vec::new();
+        for start in self.nodes() {
+            if seen[start as usize] {
+                continue;
+            }
+            seen[start as usize] = true;
+            let mut component = vec![start];
+            let mut i = 0;
+            while i < component.len() {
+                for n in self.neighbors(component[i]) {
+                    if !seen[*n as usize] {
+                        seen[*n as usize] = true;
+                        component.push(*n);
+                    }
+                }
+                i += 1;
+            }
+            component.sort();
+            components.push(component);
+        }
+        components.sort_by_key(|c| std::cmp::Reverse(c.len()));
+        components
+    }
+
+    pub fn degree(&self, id: NodeId) -> usize {
+        self.neighbors(id).len()
+    }
+
+    /// How many nodes have each degree
+    pub fn degree_distribution(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for id in self.nodes() {
+            *histogram.entry(self.degree(id)).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Core number of every node: the largest k such that the node survives in the k-core,
+    /// found by peeling off the lowest-degree node over and over
+    pub fn core_numbers(&self) -> Vec<usize> {
+        let mut degree: Vec<usize> = self.nodes().map(|id| self.degree(id)).collect();
+        let mut queue: BTreeSet<(usize, NodeId)> = self.nodes().map(|id| (degree[id as usize], id)).collect();
+        let mut core = vec![0; self.len()];
+        let mut k = 0;
+        while let Some((d, id)) = queue.pop_first() {
+            k = k.max(d);
+            core[id as usize] = k;
+            for n in self.neighbors(id) {
+                let n = *n as usize;
+                if queue.remove(&(degree[n], n as NodeId)) {
+                    degree[n] -= 1;
+                    queue.insert((degree[n], n as NodeId));
+                }
+            }
+        }
+        core
+    }
+
+    /// The nodes left after repeatedly removing anything with fewer than k neighbors
+    pub fn k_core(&self, k: usize) -> Vec<NodeId> {
+        let core = self.core_numbers();
+        self.nodes().filter(|id| core[*id as usize] >= k).collect()
+    }
+
+    // Tarjan's low-link walk, without recursion so long chains can't blow the stack.
+    // Returns the bridges and the articulation points.
+    fn cut_search(&self) -> (Vec<(NodeId, NodeId)>, Vec<NodeId>) {
+        const UNSEEN: usize = usize::MAX;
+        let mut disc = vec![UNSEEN; self.len()];
+        let mut low = vec![0; self.len()];
+        let mut bridges = Vec::new();
+        let mut cuts = BTreeSet::new();
+        let mut time = 0;
+
+        for root in self.nodes() {
+            if disc[root as usize] != UNSEEN {
+                continue;
+            }
+            disc[root as usize] = time;
+            low[root as usize] = time;
+            time += 1;
+            let mut root_children = 0;
+
+            // (node, parent, index of the next neighbor to look at)
+            let mut stack = vec![(root, root, 0)];
+            while let Some((u, parent, i)) = stack.last().copied() {
+                if let Some(v) = self.neighbors(u).get(i).copied() {
+                    stack.last_mut().unwrap().2 += 1;
+                    if disc[v as usize] == UNSEEN {
+                        disc[v as usize] = time;
+                        low[v as usize] = time;
+                        time += 1;
+                        if u == root {
+                            root_children += 1;
+                        }
+                        stack.push((v, u, 0));
+                    } else if v != parent {
+                        low[u as usize] = low[u as usize].min(disc[v as usize]);
+                    }
+                    continue;
+                }
+                // Done with u; report back to its parent
+                stack.pop();
+                if u != root {
+                    low[parent as usize] = low[parent as usize].min(low[u as usize]);
+                    if low[u as usize] > disc[parent as usize] {
+                        bridges.push((parent.min(u), parent.max(u)));
+                    }
+                    if parent != root && low[u as usize] >= disc[parent as usize] {
+                        cuts.insert(parent);
+                    }
+                }
+            }
+            if root_children > 1 {
+                cuts.insert(root);
+            }
+        }
+        bridges.sort();
+        (bridges, cuts.into_iter().collect())
+    }
+
+    /// Edges whose removal would split their component
+    pub fn bridges(&self) -> Vec<(NodeId, NodeId)> {
+        self.cut_search().0
+    }
+
+    /// Nodes whose removal would split their component
+    pub fn articulation_points(&self) -> Vec<NodeId> {
+        self.cut_search().1
+    }
+
+    /// Graphviz source for the graph, with the highlighted nodes and the edges between them in red
+    pub fn to_dot(&self, highlight: &[NodeId]) -> String {
+        let lit: HashSet<NodeId> = highlight.iter().copied().collect();
+        let mut dot = String::from("graph {\n");
+        for id in self.nodes() {
+            if lit.contains(&id) {
+                dot += &format!("  \"{}\" [style=filled, fillcolor=red];\n", self.name(id));
+            } else {
+                dot += &format!("  \"{}\";\n", self.name(id));
+            }
+        }
+        for a in self.nodes() {
+            for b in self.neighbors(a).iter().filter(|b| **b > a) {
+                let style = if lit.contains(&a) && lit.contains(b) { " [color=red, penwidth=2]" } else { "" };
+                dot += &format!("  \"{}\" -- \"{}\"{};\n", self.name(a), self.name(*b), style);
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two squares sharing an edge, with both diagonals in the left one
+    const EDGES: &[(&str, &str)] = &[
+        ("a", "b"), ("b", "c"), ("c", "d"), ("d", "a"), ("a", "c"), ("b", "d"),
+        ("c", "e"), ("e", "f"), ("f", "d"),
+        ("x", "y"),
+    ];
+
+    #[test]
+    fn interning() {
+        let graph = Graph::from_edges(EDGES.iter().copied());
+        assert_eq!(graph.len(), 8);
+        assert_eq!(graph.name(graph.id("e").unwrap()), "e");
+        assert_eq!(graph.id("z"), None);
+        let c = graph.id("c").unwrap();
+        assert_eq!(graph.names(graph.neighbors(c)), vec!["a", "b", "d", "e"]);
+    }
+
+    #[test]
+    fn maximum_matches_maximal() {
+        // Against picking the best of every maximal clique, on random graphs of varying density
+        let mut rng = crate::harness::Rng::new(39);
+        for _ in 0..100 {
+            let names: Vec<String> = (0..rng.between(1, 14)).map(|i| format!("n{:02}", i)).collect();
+            let odds = rng.between(1, 4);
+            let mut graph = Graph::new();
+            for (i, a) in names.iter().enumerate() {
+                graph.intern(a);
+                for b in names[i + 1..].iter() {
+                    if !rng.one_in(odds + 1) {
+                        graph.add_edge(a, b);
+                    }
+                }
+            }
+            for pick in ["n00", "n03"] {
+                let expected = graph.maximal_cliques().into_iter()
+                    .filter(|c| c.iter().any(|id| graph.name(*id) == pick))
+                    .map(|c| (std::cmp::Reverse(c.len()), graph.names(&c).join(",")))
+                    .min()
+                    .map(|(_, names)| names);
+                let found = graph.maximum_clique_where(|n| n == pick).map(|c| graph.names(&c).join(","));
+                assert_eq!(found, expected);
+            }
+        }
+
+        // Dense enough that listing every maximal clique would take far too long
+        let mut graph = Graph::new();
+        let names: Vec<String> = (0..60).map(|i| format!("n{:02}", i)).collect();
+        for (i, a) in names.iter().enumerate() {
+            for b in names[i + 1..].iter() {
+                if !rng.one_in(5) {
+                    graph.add_edge(a, b);
+                }
+            }
+        }
+        assert!(graph.maximum_clique().unwrap().len() > 10);
+    }
+
+    #[test]
+    fn triangles_and_cliques() {
+        let graph = Graph::from_edges(EDGES.iter().copied());
+        assert_eq!(graph.triangles().len(), 4);
+
+        let mut cliques: Vec<String> = graph.maximal_cliques().iter().map(|c| graph.names(c).join(",")).collect();
+        cliques.sort();
+        assert_eq!(cliques, vec!["a,b,c,d", "c,e", "d,f", "e,f", "x,y"]);
+
+        assert_eq!(graph.names(&graph.maximum_clique().unwrap()), vec!["a", "b", "c", "d"]);
+        assert_eq!(graph.names(&graph.maximum_clique_where(|n| n == "f").unwrap()), vec!["d", "f"]);
+        assert_eq!(graph.maximum_clique_where(|n| n == "z"), None);
+        assert_eq!(Graph::new().maximum_clique(), None);
+    }
+
+    #[test]
+    fn components_and_degrees() {
+        let graph = Graph::from_edges(EDGES.iter().copied());
+        let components: Vec<Vec<&str>> = graph.components().iter().map(|c| graph.names(c)).collect();
+        assert_eq!(components, vec![vec!["a", "b", "c", "d", "e", "f"], vec!["x", "y"]]);
+        assert_eq!(graph.degree_distribution(), BTreeMap::from([(1, 2), (2, 2), (3, 2), (4, 2)]));
+    }
+
+    #[test]
+    fn cores() {
+        let graph = Graph::from_edges(EDGES.iter().copied());
+        let core = graph.core_numbers();
+        assert_eq!(core[graph.id("a").unwrap() as usize], 3);
+        assert_eq!(core[graph.id("e").unwrap() as usize], 2);
+        assert_eq!(core[graph.id("x").unwrap() as usize], 1);
+        assert_eq!(graph.names(&graph.k_core(3)), vec!["a", "b", "c", "d"]);
+        assert_eq!(graph.k_core(4), vec![]);
+    }
+
+    #[test]
+    fn bridges_and_cuts() {
+        // A triangle with a tail, and a separate path
+        let graph = Graph::from_edges([("a", "b"), ("b", "c"), ("c", "a"), ("c", "d"), ("d", "e"), ("p", "q"), ("q", "r")]);
+        let bridges: Vec<(&str, &str)> = graph.bridges().iter().map(|(a, b)| (graph.name(*a), graph.name(*b))).collect();
+        assert_eq!(bridges, vec![("c", "d"), ("d", "e"), ("p", "q"), ("q", "r")]);
+        assert_eq!(graph.names(&graph.articulation_points()), vec!["c", "d", "q"]);
+
+        let graph = Graph::from_edges(EDGES.iter().copied());
+        assert_eq!(graph.names(&graph.articulation_points()), Vec::<&str>::new());
+        assert_eq!(graph.bridges().len(), 1);
+    }
+
+    #[test]
+    fn dot_export() {
+        let graph = Graph::from_edges([("a", "b"), ("b", "c")]);
+        let dot = graph.to_dot(&[graph.id("a").unwrap(), graph.id("b").unwrap()]);
+        assert_eq!(dot, "graph {\n  \"a\" [style=filled, fillcolor=red];\n  \"b\" [style=filled, fillcolor=red];\n  \"c\";\n  \"a\" -- \"b\" [color=red, penwidth=2];\n  \"b\" -- \"c\";\n}\n");
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::{HashMap, HashSet}, ops::Index};
+use std::{collections::{HashMap, HashSet, VecDeque}, ops::Index};
 use itertools::Itertools;
 use crate::point::Point;
 
@@ -257,6 +257,200 @@ impl Grid {
     }
 }
 
+/// A connected group of cells that all hold the same value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    /// Index of this region in its Regions
+    pub label: usize,
+    pub value: char,
+    pub cells: HashSet<Point>,
+
+    /// Number of cell edges that face out of the region
+    pub perimeter: usize,
+
+    /// Number of straight fence runs around the region, inside and out
+    pub sides: usize,
+
+    /// Bounding box, inclusive
+    pub min: Point,
+    pub max: Point,
+
+    /// Groups of foreign cells completely surrounded by this region
+    pub holes: Vec<HashSet<Point>>,
+}
+
+const STRAIGHT: [Point; 4] = [Point { x: 1, y: 0 }, Point { x: 0, y: 1 }, Point { x: -1, y: 0 }, Point { x: 0, y: -1 }];
+const DIAGONAL: [Point; 4] = [Point { x: 1, y: 1 }, Point { x: -1, y: 1 }, Point { x: -1, y: -1 }, Point { x: 1, y: -1 }];
+
+impl Region {
+    fn new(label: usize, value: char, cells: HashSet<Point>) -> Region {
+        let min = Point::new(cells.iter().map(|p| p.x).min().unwrap(), cells.iter().map(|p| p.y).min().unwrap());
+        let max = Point::new(cells.iter().map(|p| p.x).max().unwrap(), cells.iter().map(|p| p.y).max().unwrap());
+        let mut region = Region { label, value, cells, perimeter: 0, sides: 0, min, max, holes: Vec::new() };
+        region.perimeter = region.count_perimeter();
+        region.sides = region.count_corners();
+        region.holes = region.find_holes();
+        region
+    }
+
+    pub fn area(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        self.cells.contains(p)
+    }
+
+    fn count_perimeter(&self) -> usize {
+        self.cells.iter()
+            .flat_map(|p| STRAIGHT.iter().map(move |d| *p + *d))
+            .filter(|p| !self.contains(p))
+            .count()
+    }
+
+    // A polygon has as many sides as corners.  Each cell contributes a corner in a diagonal
+    // direction if both straight neighbors that way are outside (convex), or if both are
+    // inside but the diagonal cell is outside (concave).
+    fn count_corners(&self) -> usize {
+        self.cells.iter()
+            .flat_map(|p| DIAGONAL.iter().map(move |d| (*p, *d)))
+            .filter(|(p, d)| {
+                let horz = self.contains(&(*p + Point::new(d.x, 0)));
+                let vert = self.contains(&(*p + Point::new(0, d.y)));
+                let diag = self.contains(&(*p + *d));
+                (!horz && !vert) || (horz && vert && !diag)
+            })
+            .count()
+    }
+
+    // Flood the foreign cells in our bounding box from the outside in; whatever isn't reached is enclosed.
+    // Our cells connect only straight, so foreign cells may escape diagonally between them.
+    fn find_holes(&self) -> Vec<HashSet<Point>> {
+        let inside = |p: &Point| p.x >= self.min.x && p.x <= self.max.x && p.y >= self.min.y && p.y <= self.max.y;
+        let mut foreign: HashSet<Point> = (self.min.x..=self.max.x)
+            .flat_map(|x| (self.min.y..=self.max.y).map(move |y| Point::new(x, y)))
+            .filter(|p| !self.contains(p))
+            .collect();
+
+        let mut queue: VecDeque<Point> = foreign.iter()
+            .filter(|p| p.x == self.min.x || p.x == self.max.x || p.y == self.min.y || p.y == self.max.y)
+            .copied()
+            .collect();
+        for p in queue.iter() {
+            foreign.remove(p);
+        }
+        while let Some(p) = queue.pop_front() {
+            for next in STRAIGHT.iter().chain(DIAGONAL.iter()).map(|d| p + *d) {
+                if inside(&next) && foreign.remove(&next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        // Whatever is left is enclosed; group it into separate holes
+        let mut holes = Vec::new();
+        while let Some(&seed) = foreign.iter().next() {
+            foreign.remove(&seed);
+            let mut hole = HashSet::from([seed]);
+            let mut queue = VecDeque::from([seed]);
+            while let Some(p) = queue.pop_front() {
+                for next in STRAIGHT.iter().chain(DIAGONAL.iter()).map(|d| p + *d) {
+                    if foreign.remove(&next) {
+                        hole.insert(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+            holes.push(hole);
+        }
+        holes
+    }
+}
+
+/// Every connected region of a Grid, with each cell labelled by the region it belongs to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Regions {
+    pub regions: Vec<Region>,
+
+    // Region label of each cell, indexed like Grid::carte
+    labels: Vec<Vec<usize>>,
+}
+
+impl Regions {
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Region> {
+        self.regions.iter()
+    }
+
+    /// The region holding the given cell
+    pub fn at(&self, p: &Point) -> &Region {
+        &self.regions[self.labels[p.y as usize][p.x as usize]]
+    }
+}
+
+// Characters used to tell regions apart when rendering; they repeat if there are many regions
+const LABELS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+impl core::fmt::Display for Regions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let labels: Vec<char> = LABELS.chars().collect();
+        for (y, line) in self.labels.iter().enumerate().rev() {
+            for label in line.iter() {
+                write!(f, "{}", labels[label % labels.len()])?;
+            }
+            if y > 0 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Grid {
+    /// Split the grid into connected regions of equal cells, in one breadth-first pass
+    pub fn regions(&self) -> Regions {
+        let mut labels: Vec<Vec<Option<usize>>> = self.carte.iter().map(|line| vec![None; line.len()]).collect();
+        let mut regions = Vec::new();
+
+        // Label from the top left, the way the grid reads
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                let seed = Point::new(x, y);
+                if labels[y as usize][x as usize].is_some() {
+                    continue;
+                }
+                let label = regions.len();
+                let value = self[&seed];
+                labels[y as usize][x as usize] = Some(label);
+                let mut cells = HashSet::from([seed]);
+                let mut queue = VecDeque::from([seed]);
+                while let Some(p) = queue.pop_front() {
+                    for next in STRAIGHT.iter().map(|d| p + *d) {
+                        if self.valid(&next) && self[&next] == value && labels[next.y as usize][next.x as usize].is_none() {
+                            labels[next.y as usize][next.x as usize] = Some(label);
+                            cells.insert(next);
+                            queue.push_back(next);
+                        }
+                    }
+                }
+                regions.push(Region::new(label, value, cells));
+            }
+        }
+
+        Regions {
+            regions,
+            labels: labels.into_iter().map(|line| line.into_iter().map(Option::unwrap).collect()).collect(),
+        }
+    }
+}
+
 impl core::fmt::Display for Grid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for (y, line) in self.carte.iter().enumerate().rev() {
@@ -426,6 +620,57 @@ const ASYMETRIC_ROT_RIGHT3: &str = "dhl\ncgk\nbfj\naei";
 
     }
 
+    const NESTED: &str = "AAAAAA
+ABBAAA
+ABBACA
+AAAAAA";
+
+    #[test]
+    fn regions_basic() {
+        let grid = Grid::new(NESTED);
+        let regions = grid.regions();
+        assert_eq!(regions.len(), 3);
+
+        let outer = regions.at(&Point::new(0, 0));
+        assert_eq!(outer.value, 'A');
+        assert_eq!(outer.area(), 19);
+        assert_eq!(outer.perimeter, 32);
+        assert_eq!(outer.sides, 12);
+        assert_eq!(outer.min, Point::new(0, 0));
+        assert_eq!(outer.max, Point::new(5, 3));
+        assert_eq!(outer.holes.len(), 2);
+        assert_eq!(outer.holes.iter().map(|h| h.len()).sorted().collect_vec(), vec![1, 4]);
+
+        let inner = regions.at(&Point::new(2, 2));
+        assert_eq!(inner.value, 'B');
+        assert_eq!(inner.area(), 4);
+        assert_eq!(inner.perimeter, 8);
+        assert_eq!(inner.sides, 4);
+        assert!(inner.holes.is_empty());
+        assert_eq!(regions.iter().map(|r| r.area()).sum::<usize>(), 24);
+    }
+
+    #[test]
+    fn regions_diagonal_escape() {
+        // The X ring is broken at a corner, so the middle isn't enclosed
+        let grid = Grid::new("XXX.\nX.X.\nXX.X\n..XX");
+        let regions = grid.regions();
+        let ring = regions.at(&Point::new(0, 3));
+        assert_eq!(ring.area(), 7);
+        assert!(ring.holes.is_empty());
+
+        let grid = Grid::new("XXX\nX.X\nXXX");
+        let ring = grid.regions().at(&Point::new(0, 0)).clone();
+        assert_eq!(ring.holes, vec![HashSet::from([Point::new(1, 1)])]);
+        assert_eq!(ring.sides, 8);
+    }
+
+    #[test]
+    fn regions_render() {
+        let grid = Grid::new("AAB\nCAB\nCCA");
+        assert_eq!(format!("{}", grid.regions()), "aab\ncab\nccd");
+    }
+
     const COORDS: &str = "This is row 5
     abcdefghijklm
     0123456543210
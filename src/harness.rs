@@ -0,0 +1,226 @@
+use std::{fmt::Debug, panic::{catch_unwind, AssertUnwindSafe}};
+
+/// A small seeded random number generator (SplitMix64).  The same seed always gives the same numbers,
+/// so any failure can be reproduced.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0);
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A number in lo..=hi
+    pub fn between(&mut self, lo: usize, hi: usize) -> usize {
+        lo + self.below(hi - lo + 1)
+    }
+
+    /// True one time in n
+    pub fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// Smaller versions of a list: with each half removed, then with each single item removed
+pub fn shrink_vec<T: Clone>(items: &[T]) -> Vec<Vec<T>> {
+    let mut smaller = Vec::new();
+    if items.len() > 1 {
+        let half = items.len() / 2;
+        smaller.push(items[half..].to_vec());
+        smaller.push(items[..half].to_vec());
+    }
+    for i in 0..items.len() {
+        let mut fewer = items.to_vec();
+        fewer.remove(i);
+        smaller.push(fewer);
+    }
+    smaller
+}
+
+/// Smaller versions of a string: shorter from either end, or with one character removed
+pub fn shrink_string(s: &str) -> Vec<String> {
+    let chars: Vec<char> = s.chars().collect();
+    shrink_vec(&chars).into_iter().map(|c| c.into_iter().collect()).collect()
+}
+
+/// Smaller numbers, biggest jumps first
+pub fn shrink_u64(n: u64) -> Vec<u64> {
+    let mut smaller = Vec::new();
+    let mut gap = n;
+    while gap > 0 {
+        smaller.push(n - gap);
+        gap /= 2;
+    }
+    smaller
+}
+
+/// Implementations that disagreed, on the smallest input we could find
+#[derive(Clone, Debug)]
+pub struct Failure<I, O> {
+    pub seed: u64,
+    pub case: usize,
+    pub original: I,
+    pub minimal: I,
+    /// What each implementation said about the minimal input, or what it panicked with
+    pub outputs: Vec<(&'static str, Result<O, String>)>,
+}
+
+type Implementation<I, O> = Box<dyn Fn(&I) -> O>;
+
+/// Runs several implementations of the same thing on random inputs and checks that they all agree.
+/// When they don't, the input is shrunk as far as it will go while they still disagree.
+/// An implementation that panics disagrees with everything.
+pub struct Harness<I, O> {
+    implementations: Vec<(&'static str, Implementation<I, O>)>,
+}
+
+impl<I: Clone + Debug, O: PartialEq + Debug> Default for Harness<I, O> {
+    fn default() -> Self {
+        Harness::new()
+    }
+}
+
+impl<I: Clone + Debug, O: PartialEq + Debug> Harness<I, O> {
+    pub fn new() -> Harness<I, O> {
+        Harness { implementations: Vec::new() }
+    }
+
+    pub fn register(mut self, name: &'static str, f: impl Fn(&I) -> O + 'static) -> Harness<I, O> {
+        self.implementations.push((name, Box::new(f)));
+        self
+    }
+
+    /// What every implementation says about an input
+    pub fn outputs(&self, input: &I) -> Vec<(&'static str, Result<O, String>)> {
+        self.implementations.iter()
+            .map(|(name, f)| {
+                let output = catch_unwind(AssertUnwindSafe(|| f(input))).map_err(|panic| {
+                    panic.downcast_ref::<&str>().map(|s| s.to_string())
+                        .or_else(|| panic.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "panicked".to_string())
+                });
+                (*name, output)
+            })
+            .collect()
+    }
+
+    pub fn agree(&self, input: &I) -> bool {
+        let outputs = self.outputs(input);
+        outputs.iter().all(|(_, o)| o.is_ok()) && outputs.windows(2).all(|w| w[0].1 == w[1].1)
+    }
+
+    /// Try `cases` random inputs from `generate`.  On a disagreement, keep taking the first smaller
+    /// input from `shrink` that still disagrees until there isn't one.
+    pub fn check(
+        &self,
+        seed: u64,
+        cases: usize,
+        generate: impl Fn(&mut Rng) -> I,
+        shrink: impl Fn(&I) -> Vec<I>,
+    ) -> Result<(), Failure<I, O>> {
+        let mut rng = Rng::new(seed);
+        for case in 0..cases {
+            let original = generate(&mut rng);
+            if self.agree(&original) {
+                continue;
+            }
+            let mut minimal = original.clone();
+            while let Some(smaller) = shrink(&minimal).into_iter().find(|i| !self.agree(i)) {
+                minimal = smaller;
+            }
+            let outputs = self.outputs(&minimal);
+            return Err(Failure { seed, case, original, minimal, outputs });
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng_is_reproducible() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        assert!((0..100).all(|_| a.next_u64() == b.next_u64()));
+        assert!((0..1000).all(|_| a.between(3, 7) >= 3 && a.between(3, 7) <= 7));
+        let mut items: Vec<u32> = (0..20).collect();
+        a.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn shrinks_to_minimal() {
+        // A sum that forgets about anything over 100
+        let harness = Harness::new()
+            .register("sum", |v: &Vec<u64>| v.iter().sum::<u64>())
+            .register("buggy", |v: &Vec<u64>| v.iter().filter(|n| **n <= 100).sum::<u64>());
+        let shrink = |v: &Vec<u64>| {
+            let mut smaller = shrink_vec(v);
+            for i in 0..v.len() {
+                smaller.extend(shrink_u64(v[i]).into_iter().map(|n| {
+                    let mut v = v.clone();
+                    v[i] = n;
+                    v
+                }));
+            }
+            smaller
+        };
+        let failure = harness.check(7, 100, |rng| (0..rng.below(10)).map(|_| rng.below(200) as u64).collect(), shrink)
+            .unwrap_err();
+        assert_eq!(failure.minimal, vec![101]);
+        assert_eq!(failure.outputs, vec![("sum", Ok(101)), ("buggy", Ok(0))]);
+
+        let panicky = Harness::new()
+            .register("sum", |v: &Vec<u64>| v.iter().sum::<u64>())
+            .register("first", |v: &Vec<u64>| v[0] + v[1..].iter().sum::<u64>());
+        let failure = panicky.check(7, 100, |rng| (0..rng.below(10)).map(|_| rng.below(200) as u64).collect(), shrink)
+            .unwrap_err();
+        assert_eq!(failure.minimal, vec![]);
+        assert!(failure.outputs[1].1.as_ref().unwrap_err().contains("index out of bounds"));
+
+        let fixed = Harness::new()
+            .register("sum", |v: &Vec<u64>| v.iter().sum::<u64>())
+            .register("loop", |v: &Vec<u64>| {
+                let mut total = 0;
+                for n in v {
+                    total += n;
+                }
+                total
+            });
+        assert!(fixed.check(7, 100, |rng| vec![rng.below(200) as u64], shrink).is_ok());
+    }
+
+    #[test]
+    fn shrinkers() {
+        assert_eq!(shrink_u64(8), vec![0, 4, 6, 7]);
+        assert_eq!(shrink_string("abc"), vec!["bc", "a", "bc", "ac", "ab"]);
+    }
+}
@@ -1,13 +1,13 @@
-mod day23;
-mod day22;
-mod day21;
-// mod day20;
-mod day19;
-mod day18;
+pub mod day23;
+pub mod day22;
+pub mod day21;
+pub mod day20;
+pub mod day19;
+pub mod day18;
 mod day17;
 mod day16;
-mod day15;
-mod day14;
+pub mod day15;
+pub mod day14;
 mod day13;
 extern crate aoc_runner;
 
@@ -23,5 +23,10 @@ pub mod day12;
 pub mod grid;
 pub mod point;
 pub mod line;
+pub mod evolver;
+pub mod disjoint_set;
+pub mod graph;
+pub mod harness;
+pub mod gen;
 
 aoc_lib!{ year = 2024 }
\ No newline at end of file
@@ -26,6 +26,28 @@ impl Line {
         }
     }
 
+    /// The smallest whole-number step from one point on the line to the next
+    pub fn step(&self) -> Point {
+        let (run, rise) = self.slope();
+        Point::new(run, rise)
+    }
+
+    /// Every whole-number point on the line where `valid` holds, walking both ways from p1 until
+    /// it doesn't.  In order along the line.
+    pub fn lattice_points(&self, valid: impl Fn(&Point) -> bool) -> Vec<Point> {
+        let step = self.step();
+        let mut back: Vec<Point> = (1..)
+            .map(|i| self.p1 - step * i)
+            .take_while(&valid)
+            .collect();
+        back.reverse();
+        let ahead = (0..)
+            .map(|i| self.p1 + step * i)
+            .take_while(&valid);
+        back.extend(ahead);
+        back
+    }
+
     pub fn intersect(&self, other: &Line) -> Option<Point> {
         // Find line intersection
         let d1 = self.p2 - self.p1;
@@ -105,6 +127,18 @@ mod tests {
 ............
 ............";
 
+    #[test]
+    fn lattice() {
+        let line = Line::new(Point::new(1, 1), Point::new(3, 5));
+        assert_eq!(line.step(), Point::new(1, 2));
+        let inside = |p: &Point| p.x >= 0 && p.x < 5 && p.y >= 0 && p.y < 8;
+        assert_eq!(line.lattice_points(inside), vec![Point::new(1, 1), Point::new(2, 3), Point::new(3, 5), Point::new(4, 7)]);
+
+        let line = Line::new(Point::new(4, 0), Point::new(0, 2));
+        assert_eq!(line.step(), Point::new(2, -1));
+        assert_eq!(line.lattice_points(inside), vec![Point::new(0, 2), Point::new(2, 1), Point::new(4, 0)]);
+    }
+
     #[test]
     fn sample1() {
         let game = Grid::new(SAMPLE);